@@ -0,0 +1,347 @@
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::docker_compose::{self, Environment};
+use crate::dockerfile::{self, instructions, variables, BuildArg, CheckError, ImageReference};
+use crate::pattern::Presets;
+use crate::suggest;
+
+/// The annotations to insert into a file, and the images without annotation that none can be inserted for.
+#[derive(Debug, Default, PartialEq)]
+pub struct Annotations {
+    pub insertions: Vec<Insertion>,
+    pub skipped: Vec<(ImageReference, Skip)>,
+}
+
+/// Why no annotation is inserted for an image without annotation.
+#[derive(Debug, Error, PartialEq)]
+pub enum Skip {
+    #[error("No pattern can be inferred from the tag `{0}`")]
+    NoPattern(String),
+    #[error(transparent)]
+    Unresolved(CheckError),
+}
+
+impl Annotations {
+    /// Adds the annotation inferred from the `tag`, unless no pattern can be inferred.
+    fn add(&mut self, input: &str, reference: ImageReference, tag: &str, line_start: usize) {
+        match Insertion::new(input, reference.clone(), tag, line_start) {
+            Some(insertion) => self.insertions.push(insertion),
+            None => self
+                .skipped
+                .push((reference, Skip::NoPattern(tag.to_string()))),
+        }
+    }
+}
+
+/// An annotation to insert above an image that is not annotated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Insertion {
+    pub reference: ImageReference,
+    /// The part of the tag that the pattern has to match.
+    pub tag: String,
+    /// The pattern or preset name to annotate the image with.
+    pub pattern: String,
+    /// The start of the line that the annotation is inserted above.
+    pub offset: usize,
+    /// The indentation of that line, which the annotation shares.
+    pub indentation: String,
+}
+
+impl Insertion {
+    fn new(input: &str, reference: ImageReference, tag: &str, line_start: usize) -> Option<Self> {
+        let pattern = suggest::candidates(tag).into_iter().next()?.to_string();
+        let indentation = input[line_start..]
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        Some(Insertion {
+            reference,
+            tag: tag.to_string(),
+            pattern,
+            offset: line_start,
+            indentation,
+        })
+    }
+
+    /// The line of the annotation, including the line break.
+    pub fn comment(&self) -> String {
+        format!(
+            "{}# uptag --pattern \"{}\"\n",
+            self.indentation, self.pattern
+        )
+    }
+}
+
+/// The part of the tag that a pattern inferred for the `reference` has to match.
+///
+/// For a tag held in a variable, this is the variable's value, since the annotation is inserted above its declaration.
+fn written_tag(reference: &ImageReference) -> &str {
+    let tag = &reference.image.tag;
+    match &reference.variable {
+        Some(variable) => tag
+            .strip_prefix(variable.prefix.as_str())
+            .and_then(|tag| tag.strip_suffix(variable.suffix.as_str()))
+            .unwrap_or(tag),
+        None => tag,
+    }
+}
+
+/// Finds the images in a Dockerfile without annotation and infers their patterns.
+///
+/// See [`dockerfile_with_args`].
+pub fn dockerfile(file: &Path, input: &str, presets: &Presets) -> Annotations {
+    dockerfile_with_args(file, input, presets, &[])
+}
+
+/// Finds the images in a Dockerfile built with the `build_args` without annotation and infers their patterns.
+///
+/// The annotations are inserted above the instructions containing the images, or above the declarations of the
+/// variables holding their tags, even if a build argument overrides the variable. Images whose tag contains no
+/// numbers, such as `latest`, and images that cannot be resolved are skipped.
+pub fn dockerfile_with_args(
+    file: &Path,
+    input: &str,
+    presets: &Presets,
+    build_args: &[BuildArg],
+) -> Annotations {
+    let instructions = instructions::tokenize(input);
+    let mut annotations = Annotations::default();
+    for (reference, annotation) in dockerfile::parse_with_args(file, input, presets, build_args) {
+        match annotation {
+            Err(CheckError::UnspecifiedPattern) => {}
+            Err(error @ CheckError::UnresolvedVariable(_)) => {
+                annotations
+                    .skipped
+                    .push((reference, Skip::Unresolved(error)));
+                continue;
+            }
+            _ => continue,
+        }
+        let instruction = if reference.location.file == file {
+            let position = reference.location.span.start;
+            instructions.iter().find(|instruction| {
+                instruction.span.start <= position && position < instruction.span.end
+            })
+        } else {
+            // A build argument holds the tag, so the `ARG` declaring it is annotated.
+            let name = reference
+                .variable
+                .as_ref()
+                .map(|variable| variable.name.as_str());
+            instructions
+                .iter()
+                .take_while(|instruction| !instruction.is("FROM"))
+                .filter(|instruction| instruction.is("ARG"))
+                .filter(|instruction| {
+                    variables::declarations(input, instruction)
+                        .iter()
+                        .any(|declaration| Some(declaration.name) == name)
+                })
+                .last()
+        };
+        let line_start = match instruction {
+            Some(instruction) => line_start(input, instruction.span.start),
+            None => continue,
+        };
+        // Several images may share a variable, which needs only one annotation.
+        if annotations
+            .insertions
+            .iter()
+            .any(|insertion| insertion.offset == line_start)
+        {
+            continue;
+        }
+        let tag = written_tag(&reference).to_string();
+        annotations.add(input, reference, &tag, line_start);
+    }
+    annotations
+}
+
+/// Finds the images in `image` fields and `docker-image://` additional contexts of a docker-compose file without
+/// annotation and infers their patterns.
+///
+/// The annotations are inserted above the fields, even if a variable holds the tag. Images whose tag contains no
+/// numbers, such as `latest`, are skipped.
+pub fn docker_compose(
+    file: &Path,
+    input: &str,
+    presets: &Presets,
+    environment: &Environment,
+) -> Result<Annotations, docker_compose::Error> {
+    let mut annotations = Annotations::default();
+    for (reference, field) in docker_compose::unannotated_images(file, input, presets, environment)?
+    {
+        let line_start = line_start(input, field.span.start);
+        let tag = reference.image.tag.clone();
+        annotations.add(input, reference, &tag, line_start);
+    }
+    Ok(annotations)
+}
+
+/// Inserts the annotations of the `insertions` into the `input`, keeping everything else as it is.
+pub fn insert(input: &str, insertions: &[Insertion]) -> String {
+    let mut insertions = insertions.iter().collect::<Vec<_>>();
+    insertions.sort_by_key(|insertion| insertion.offset);
+
+    let mut output = String::with_capacity(input.len());
+    let mut end = 0;
+    for insertion in insertions {
+        output.push_str(&input[end..insertion.offset]);
+        output.push_str(&insertion.comment());
+        end = insertion.offset;
+    }
+    output.push_str(&input[end..]);
+    output
+}
+
+/// The start of the line containing the byte at `offset`.
+fn line_start(input: &str, offset: usize) -> usize {
+    input[..offset].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::display_error;
+    use crate::location::Location;
+
+    #[test]
+    fn annotates_dockerfiles() {
+        let input = r#"ARG NODE_VERSION=18.17.0
+  FROM node:${NODE_VERSION}-slim AS build
+COPY --from=golang:1.21-alpine /go /go
+
+# uptag --pattern "<!>.<>"
+FROM ubuntu:18.04
+FROM alpine
+"#;
+        let annotations = dockerfile(Path::new("Dockerfile"), input, &Presets::builtin());
+
+        assert_eq!(
+            insert(input, &annotations.insertions),
+            r#"# uptag --pattern "<!>.<>.<>"
+ARG NODE_VERSION=18.17.0
+  FROM node:${NODE_VERSION}-slim AS build
+# uptag --pattern "<!>.<>-alpine"
+COPY --from=golang:1.21-alpine /go /go
+
+# uptag --pattern "<!>.<>"
+FROM ubuntu:18.04
+FROM alpine
+"#
+        );
+        assert_eq!(
+            annotations
+                .skipped
+                .iter()
+                .map(|(reference, skip)| (reference.to_string(), skip))
+                .collect::<Vec<_>>(),
+            vec![(
+                "alpine:latest (Dockerfile:7:6)".to_string(),
+                &Skip::NoPattern("latest".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn annotates_args_overridden_by_build_args() {
+        let input = r#"ARG PY_VERSION=3.11.0
+ARG NODE_VERSION
+FROM python:${PY_VERSION}-slim
+FROM node:${NODE_VERSION}
+"#;
+        let compose = "args:\n  PY_VERSION: 3.12.1\n";
+        let build_args = vec![BuildArg {
+            name: "PY_VERSION".to_string(),
+            value: "3.12.1".to_string(),
+            location: Location::new(Path::new("docker-compose.yml"), compose, 20..26),
+        }];
+        let annotations = dockerfile_with_args(
+            Path::new("Dockerfile"),
+            input,
+            &Presets::builtin(),
+            &build_args,
+        );
+
+        assert_eq!(
+            insert(input, &annotations.insertions),
+            r#"# uptag --pattern "<!>.<>.<>"
+ARG PY_VERSION=3.11.0
+ARG NODE_VERSION
+FROM python:${PY_VERSION}-slim
+FROM node:${NODE_VERSION}
+"#
+        );
+        assert_eq!(
+            annotations
+                .skipped
+                .iter()
+                .map(|(reference, skip)| (reference.to_string(), display_error(skip)))
+                .collect::<Vec<_>>(),
+            vec![(
+                "node:${NODE_VERSION} (Dockerfile:4:6)".to_string(),
+                "Failed to substitute the variables, which have to be set by an `ARG` before the first `FROM`: The variable `NODE_VERSION` is not set".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn annotates_compose_files() {
+        let input = r#"services:
+  node:
+    image: "node:14.5.0-slim"
+  ubuntu:
+    # uptag --pattern "<!>.<>"
+    image: ubuntu:18.04
+  redis:
+    image: redis:7 # A comment that is kept.
+  app:
+    build:
+      additional_contexts:
+        base: docker-image://alpine:3.18
+        tools: docker-image://busybox
+"#;
+        let annotations = docker_compose(
+            Path::new("docker-compose.yml"),
+            input,
+            &Presets::builtin(),
+            &Environment::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            insert(input, &annotations.insertions),
+            r#"services:
+  node:
+    # uptag --pattern "<!>.<>.<>-slim"
+    image: "node:14.5.0-slim"
+  ubuntu:
+    # uptag --pattern "<!>.<>"
+    image: ubuntu:18.04
+  redis:
+    # uptag --pattern "<!>"
+    image: redis:7 # A comment that is kept.
+  app:
+    build:
+      additional_contexts:
+        # uptag --pattern "<!>.<>"
+        base: docker-image://alpine:3.18
+        tools: docker-image://busybox
+"#
+        );
+        assert_eq!(
+            annotations
+                .skipped
+                .iter()
+                .map(|(reference, skip)| (reference.to_string(), skip))
+                .collect::<Vec<_>>(),
+            vec![(
+                "busybox:latest (docker-compose.yml:13:31)".to_string(),
+                &Skip::NoPattern("latest".to_string())
+            )]
+        );
+    }
+}
@@ -0,0 +1,483 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::image::ImageName;
+use crate::pattern::{self, Presets};
+use crate::version::extractor::{self, VersionExtractor};
+use crate::{Ignore, InvalidMinAge, MinAge, UpdateOptions};
+
+type Tag = String;
+
+/// Everything an annotation specifies about how to check an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub extractor: VersionExtractor,
+    /// Overrides `UpdateOptions::min_age` for this image.
+    pub min_age: Option<MinAge>,
+    /// Tags that must not be suggested, in addition to `UpdateOptions::ignore`.
+    pub ignore: Vec<Tag>,
+    /// The image whose tag is held in an annotated variable.
+    pub image: Option<ImageName>,
+    /// Overrides how many tags are fetched before stopping the search.
+    pub search_limit: Option<usize>,
+    /// A note that is shown next to the image in reports.
+    pub note: Option<String>,
+}
+
+lazy_static! {
+    static ref COMMENT: Regex = Regex::new(r#"^\s*uptag(?P<arguments>(\s.*)?)$"#).unwrap();
+    static ref BLOCK_START: Regex = Regex::new(r#"^\s*#\s*uptag\s+images:\s*$"#).unwrap();
+    static ref BLOCK_ENTRY: Regex =
+        Regex::new(r#"^\s*#\s*(?P<image>[^\s:]+):(?P<arguments>\s+--.*)$"#).unwrap();
+}
+
+impl Annotation {
+    pub fn new(extractor: VersionExtractor) -> Self {
+        Annotation {
+            extractor,
+            min_age: None,
+            ignore: Vec::new(),
+            image: None,
+            search_limit: None,
+            note: None,
+        }
+    }
+
+    /// Applies the annotation's options on top of the `defaults`.
+    pub fn options(&self, defaults: &UpdateOptions) -> UpdateOptions {
+        let mut ignore = defaults.ignore.clone();
+        ignore.extend(self.ignore.iter().map(|tag| Ignore::new(tag.clone())));
+
+        UpdateOptions {
+            min_age: self.min_age.or(defaults.min_age),
+            ignore,
+            ..defaults.clone()
+        }
+    }
+}
+
+/// An annotation as written in a comment, before its arguments are parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawAnnotation<'a> {
+    /// The arguments following `uptag`, e.g., ` --pattern "<!>.<>" --min-age 7d`.
+    pub arguments: &'a str,
+    /// The line of the annotation, starting at 1.
+    pub line: usize,
+}
+
+impl<'a> RawAnnotation<'a> {
+    /// Reads the annotation in a comment without its leading `#`, e.g., ` uptag --pattern "<!>.<>"`.
+    ///
+    /// Every comment starting with `uptag` is an annotation, except for the start of an `AnnotationBlock`.
+    pub fn from_comment(comment: &'a str, line: usize) -> Option<Self> {
+        if BLOCK_START.is_match(&format!("#{}", comment)) {
+            return None;
+        }
+        COMMENT.captures(comment).map(|captures| RawAnnotation {
+            arguments: captures.name("arguments").unwrap().as_str(), // The group always participates, although it may be empty.
+            line,
+        })
+    }
+
+    /// Reads the last annotation among the `comments` and their lines, which is the one closest to
+    /// the annotated line.
+    pub fn last_of<I>(comments: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = (&'a str, usize)>,
+        I::IntoIter: DoubleEndedIterator,
+    {
+        comments
+            .into_iter()
+            .rev()
+            .find_map(|(comment, line)| RawAnnotation::from_comment(comment, line))
+    }
+
+    /// The value of the `--image` option, read without parsing the other arguments.
+    ///
+    /// This names the annotated image even if the annotation is invalid.
+    pub fn image(&self) -> Option<String> {
+        let arguments = split_arguments(self.arguments)?;
+        let mut arguments = arguments.iter();
+        while let Some(argument) = arguments.next() {
+            if let Some(value) = argument.strip_prefix("--image=") {
+                return Some(value.to_string());
+            }
+            if argument == "--image" {
+                return arguments.next().cloned();
+            }
+        }
+        None
+    }
+
+    /// Parses the arguments into an annotation.
+    ///
+    /// Returns `None` if the image should not be checked at all, as requested by `--skip`.
+    pub fn parse(&self, presets: &Presets) -> Result<Option<Annotation>, Error> {
+        let line = self.line;
+        let mut arguments = split_arguments(self.arguments)
+            .ok_or(Error::UnterminatedQuote { line })?
+            .into_iter();
+
+        let mut extractor = None;
+        let mut skip = false;
+        let mut min_age = None;
+        let mut ignore = Vec::new();
+        let mut image = None;
+        let mut search_limit = None;
+        let mut note = None;
+        while let Some(argument) = arguments.next() {
+            let option = argument
+                .strip_prefix("--")
+                .ok_or_else(|| Error::UnexpectedArgument {
+                    argument: argument.clone(),
+                    line,
+                })?;
+            let (name, inline_value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (option, None),
+            };
+            if name == "skip" {
+                if inline_value.is_some() {
+                    return Err(Error::UnexpectedValue {
+                        option: name.to_string(),
+                        line,
+                    });
+                }
+                skip = true;
+                continue;
+            }
+            if !OPTIONS.contains(&name) {
+                return Err(Error::UnknownOption {
+                    option: name.to_string(),
+                    line,
+                });
+            }
+            let value =
+                inline_value
+                    .or_else(|| arguments.next())
+                    .ok_or_else(|| Error::MissingValue {
+                        option: name.to_string(),
+                        line,
+                    })?;
+
+            match name {
+                "pattern" | "regex" if extractor.is_some() => {
+                    return Err(Error::MultiplePatterns { line })
+                }
+                "pattern" => {
+                    extractor = Some(presets.parse(&value).map(VersionExtractor::new).map_err(
+                        |error| Error::InvalidPattern {
+                            pattern: value,
+                            source: error,
+                        },
+                    )?)
+                }
+                "regex" => {
+                    extractor = Some(VersionExtractor::from_regex(&value).map_err(|error| {
+                        Error::InvalidRegex {
+                            regex: value,
+                            source: error,
+                        }
+                    })?)
+                }
+                "min-age" => {
+                    min_age = Some(
+                        value
+                            .parse()
+                            .map_err(|source| Error::InvalidMinAge { line, source })?,
+                    )
+                }
+                "ignore" => ignore.push(value),
+                "image" => {
+                    image = Some(
+                        value
+                            .parse()
+                            .map_err(|_| Error::InvalidImage { value, line })?,
+                    )
+                }
+                "search-limit" => {
+                    search_limit = Some(
+                        value
+                            .parse()
+                            .map_err(|_| Error::InvalidSearchLimit { value, line })?,
+                    )
+                }
+                "note" => note = Some(value),
+                _ => unreachable!(), // Unknown options were rejected above.
+            }
+        }
+
+        if skip {
+            return Ok(None);
+        }
+        Ok(Some(Annotation {
+            extractor: extractor.ok_or(Error::MissingPattern { line })?,
+            min_age,
+            ignore,
+            image,
+            search_limit,
+            note,
+        }))
+    }
+}
+
+/// The options that take a value. `--skip` is the only flag.
+const OPTIONS: &[&str] = &[
+    "pattern",
+    "regex",
+    "min-age",
+    "ignore",
+    "image",
+    "search-limit",
+    "note",
+];
+
+/// Splits the `text` at whitespace, keeping double-quoted parts together without their quotes.
+///
+/// Returns `None` if a quote is not closed.
+fn split_arguments(text: &str) -> Option<Vec<String>> {
+    let mut arguments = Vec::new();
+    let mut current: Option<String> = None;
+    let mut is_quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                is_quoted = !is_quoted;
+                current.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !is_quoted => arguments.extend(current.take()),
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if is_quoted {
+        return None;
+    }
+    arguments.extend(current);
+    Some(arguments)
+}
+
+/// Annotations for images by name, given in a block of comments anywhere in a file:
+///
+/// ```text
+/// # uptag images:
+/// #   node: --pattern "<!>.<>.<>"
+/// #   ubuntu: --pattern "<!>.<>" --min-age 7d
+/// ```
+///
+/// They apply to images that are not annotated themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AnnotationBlock<'a> {
+    entries: Vec<(ImageName, RawAnnotation<'a>)>,
+}
+
+impl<'a> AnnotationBlock<'a> {
+    /// Finds the entries of all blocks in the `input`. Entries with invalid image names are skipped.
+    pub fn find(input: &'a str) -> Self {
+        let mut entries = Vec::new();
+        let mut lines = input.lines().enumerate();
+        while lines.any(|(_, line)| BLOCK_START.is_match(line)) {
+            for (index, captures) in lines
+                .by_ref()
+                .map_while(|(index, line)| Some((index, BLOCK_ENTRY.captures(line)?)))
+            {
+                if let Some(image) = ImageName::parse(&captures["image"]) {
+                    let annotation = RawAnnotation {
+                        arguments: captures.name("arguments").unwrap().as_str(), // The group is required for the regex to match.
+                        line: index + 1,
+                    };
+                    entries.push((image, annotation));
+                }
+            }
+        }
+        AnnotationBlock { entries }
+    }
+
+    /// The annotation for the image, where later entries take precedence.
+    pub fn get(&self, image: &ImageName) -> Option<&RawAnnotation<'a>> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|(name, _)| name == image)
+            .map(|(_, annotation)| annotation)
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    #[error("The version pattern `{pattern}` is invalid")]
+    InvalidPattern {
+        pattern: String,
+        #[source]
+        source: pattern::Error,
+    },
+    #[error("The version regex `{regex}` is invalid")]
+    InvalidRegex {
+        regex: String,
+        #[source]
+        source: extractor::RegexError,
+    },
+    #[error("The minimum age in the annotation on line {line} is invalid")]
+    InvalidMinAge {
+        line: usize,
+        #[source]
+        source: InvalidMinAge,
+    },
+    #[error("The image name `{value}` in the annotation on line {line} is invalid")]
+    InvalidImage { value: String, line: usize },
+    #[error("The search limit `{value}` in the annotation on line {line} is not a number")]
+    InvalidSearchLimit { value: String, line: usize },
+    #[error("The annotation on line {line} specifies neither `--pattern` nor `--regex`")]
+    MissingPattern { line: usize },
+    #[error("The annotation on line {line} specifies more than one pattern or regex")]
+    MultiplePatterns { line: usize },
+    #[error("Unknown option `--{option}` in the annotation on line {line}")]
+    UnknownOption { option: String, line: usize },
+    #[error("The option `--{option}` in the annotation on line {line} requires a value")]
+    MissingValue { option: String, line: usize },
+    #[error("The flag `--{option}` in the annotation on line {line} does not take a value")]
+    UnexpectedValue { option: String, line: usize },
+    #[error("Unexpected argument `{argument}` in the annotation on line {line}")]
+    UnexpectedArgument { argument: String, line: usize },
+    #[error("The annotation on line {line} contains an unclosed quote")]
+    UnterminatedQuote { line: usize },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(arguments: &str) -> Result<Option<Annotation>, Error> {
+        RawAnnotation { arguments, line: 3 }.parse(&Presets::builtin())
+    }
+
+    #[test]
+    fn parses_options() {
+        assert_eq!(
+            parse(
+                r#" --pattern "<!>.<>" --ignore "3.11.2" --min-age 7d --ignore 3.11.3 --image library --search-limit=500 --note "Pinned by ops""#
+            ),
+            Ok(Some(Annotation {
+                extractor: VersionExtractor::parse("<!>.<>").unwrap(),
+                min_age: Some(MinAge::days(7)),
+                ignore: vec!["3.11.2".to_string(), "3.11.3".to_string()],
+                image: Some(ImageName::new(None, "library".to_string())),
+                search_limit: Some(500),
+                note: Some("Pinned by ops".to_string()),
+            }))
+        );
+        assert_eq!(parse(" --skip"), Ok(None));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert_eq!(
+            parse(r#" --pattern "<!>" --frobnicate"#),
+            Err(Error::UnknownOption {
+                option: "frobnicate".to_string(),
+                line: 3
+            })
+        );
+        assert_eq!(
+            parse(r#" --pattern "<!>" --ignore"#),
+            Err(Error::MissingValue {
+                option: "ignore".to_string(),
+                line: 3
+            })
+        );
+        assert_eq!(
+            parse(r#" "<!>""#),
+            Err(Error::UnexpectedArgument {
+                argument: "<!>".to_string(),
+                line: 3
+            })
+        );
+        assert_eq!(
+            parse(" --min-age 7d"),
+            Err(Error::MissingPattern { line: 3 })
+        );
+        assert_eq!(
+            parse(r#" --pattern "<!>"#),
+            Err(Error::UnterminatedQuote { line: 3 })
+        );
+        assert_eq!(
+            parse(r#" --pattern "<!>" --search-limit many"#),
+            Err(Error::InvalidSearchLimit {
+                value: "many".to_string(),
+                line: 3
+            })
+        );
+        assert_eq!(
+            parse(r#" --pattern "<!>" --min-age 7w"#),
+            Err(Error::InvalidMinAge {
+                line: 3,
+                source: "7w".parse::<MinAge>().unwrap_err()
+            })
+        );
+        assert_eq!(
+            parse(r#" --pattern "<!>" --image a/b/c/d"#),
+            Err(Error::InvalidImage {
+                value: "a/b/c/d".to_string(),
+                line: 3
+            })
+        );
+        for flag in &["--skip=false", "--skip=no"] {
+            assert_eq!(
+                parse(&format!(r#" --pattern "<!>" {}"#, flag)),
+                Err(Error::UnexpectedValue {
+                    option: "skip".to_string(),
+                    line: 3
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn reads_annotations_from_comments() {
+        assert_eq!(
+            RawAnnotation::from_comment(r#" uptag --regex "(\d+)" --min-age 7d "#, 1),
+            Some(RawAnnotation {
+                arguments: r#" --regex "(\d+)" --min-age 7d "#,
+                line: 1,
+            })
+        );
+        assert_eq!(RawAnnotation::from_comment(" Build the app.", 1), None);
+        assert_eq!(RawAnnotation::from_comment(" uptagged", 1), None);
+        assert_eq!(RawAnnotation::from_comment(" uptag images:", 1), None);
+        assert_eq!(
+            RawAnnotation::last_of(vec![
+                (r#" uptag --pattern "<>""#, 1),
+                (r#" uptag --pattern "<!>""#, 2)
+            ])
+            .map(|annotation| annotation.line),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn finds_annotation_blocks() {
+        let input = r#"
+# uptag images:
+#   node: --pattern "<!>.<>.<>"
+#   bitnami/redis: --pattern "<!>.<>" --min-age 7d
+#   invalid/image/name: --pattern "<!>"
+FROM node:18.17.0
+"#;
+        let block = AnnotationBlock::find(input);
+        let node = ImageName::new(None, "node".to_string());
+        let redis = ImageName::new(Some("bitnami".to_string()), "redis".to_string());
+
+        assert_eq!(
+            block.get(&node),
+            Some(&RawAnnotation {
+                arguments: r#" --pattern "<!>.<>.<>""#,
+                line: 3
+            })
+        );
+        assert_eq!(
+            block.get(&redis).map(|raw| raw.arguments),
+            Some(r#" --pattern "<!>.<>" --min-age 7d"#)
+        );
+        assert_eq!(block.get(&ImageName::new(None, "ubuntu".to_string())), None);
+    }
+}
@@ -0,0 +1,188 @@
+use chrono::NaiveDate;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::image::ImageName;
+use crate::pattern::{self, Presets};
+use crate::{Ignore, UpdateOptions};
+
+/// Settings shared across all checked files.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Config {
+    pub presets: Presets,
+    pub ignore: Vec<IgnoreEntry>,
+}
+
+/// A tag of an image that must not be suggested as an update.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IgnoreEntry {
+    pub image: ImageName,
+    pub tag: String,
+    pub reason: Option<String>,
+    /// The last day on which the entry applies.
+    pub expires: Option<NaiveDate>,
+}
+
+// The configuration file's format.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    #[serde(default)]
+    presets: IndexMap<String, String>,
+    #[serde(default)]
+    ignore: Vec<RawIgnoreEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawIgnoreEntry {
+    image: String,
+    tag: String,
+    reason: Option<String>,
+    expires: Option<NaiveDate>,
+}
+
+impl Config {
+    /// Parses a YAML configuration file.
+    ///
+    /// Example:
+    /// ```yaml
+    /// presets:
+    ///   debian: "<!>.<>-bookworm"
+    /// ignore:
+    ///   - image: python
+    ///     tag: "3.11.2"
+    ///     reason: Yanked upstream
+    ///     expires: 2023-06-30
+    /// ```
+    pub fn parse(input: &str) -> Result<Config, Error> {
+        let raw: RawConfig = serde_yaml::from_str(input)?;
+
+        let mut presets = Presets::builtin();
+        for (name, raw_pattern) in raw.presets {
+            presets
+                .insert(&name, &raw_pattern)
+                .map_err(|error| Error::InvalidPreset {
+                    name,
+                    pattern: raw_pattern,
+                    source: error,
+                })?;
+        }
+
+        let ignore = raw
+            .ignore
+            .into_iter()
+            .map(|entry| {
+                let image = entry
+                    .image
+                    .parse()
+                    .map_err(|_| Error::InvalidIgnoredImage(entry.image.clone()))?;
+                Ok(IgnoreEntry {
+                    image,
+                    tag: entry.tag,
+                    reason: entry.reason,
+                    expires: entry.expires,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Config { presets, ignore })
+    }
+
+    /// Adds the unexpired ignore entries for the `image` to the `defaults`.
+    pub fn update_options(&self, image: &ImageName, defaults: &UpdateOptions) -> UpdateOptions {
+        let today = defaults.now.date_naive();
+        let mut options = defaults.clone();
+        options.ignore.extend(
+            self.ignore
+                .iter()
+                .filter(|entry| &entry.image == image)
+                .filter(|entry| entry.expires.is_none_or(|expires| today <= expires))
+                .map(|entry| Ignore {
+                    tag: entry.tag.clone(),
+                    reason: entry.reason.clone(),
+                }),
+        );
+        options
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("The configuration is malformed")]
+    Malformed(#[from] serde_yaml::Error),
+    #[error("The pattern `{pattern}` for preset `{name}` is invalid")]
+    InvalidPreset {
+        name: String,
+        pattern: String,
+        #[source]
+        source: pattern::Error,
+    },
+    #[error("The image `{0}` of an ignore entry is invalid")]
+    InvalidIgnoredImage(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::pattern::Pattern;
+
+    #[test]
+    fn parses_presets() {
+        let input = r#"
+presets:
+  debian: "<!>.<>-bookworm"
+"#;
+        let config = Config::parse(input).unwrap();
+        assert_eq!(
+            config.presets.parse("debian").unwrap().parts(),
+            Pattern::parse("<!>.<>-bookworm").unwrap().parts()
+        );
+        assert!(config.presets.get("semver").is_some());
+    }
+
+    #[test]
+    fn applies_unexpired_ignore_entries() {
+        use chrono::{TimeZone, Utc};
+
+        let input = r#"
+ignore:
+  - image: python
+    tag: "3.11.2"
+    reason: Yanked upstream
+  - image: python
+    tag: "3.10.9"
+    expires: 2022-06-01
+  - image: node
+    tag: "18.0.0"
+"#;
+        let config = Config::parse(input).unwrap();
+        let defaults = UpdateOptions {
+            now: Utc.with_ymd_and_hms(2022, 6, 10, 12, 0, 0).unwrap(),
+            ..UpdateOptions::default()
+        };
+
+        let options = config.update_options(&"python".parse().unwrap(), &defaults);
+        assert_eq!(
+            options.ignore,
+            vec![Ignore {
+                tag: "3.11.2".to_string(),
+                reason: Some("Yanked upstream".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_preset() {
+        let input = r#"
+presets:
+  broken: "<!"
+"#;
+        assert!(matches!(
+            Config::parse(input),
+            Err(Error::InvalidPreset { name, .. }) if name == "broken"
+        ));
+    }
+}
@@ -1,74 +1,451 @@
-use std::path::PathBuf;
+pub mod environment;
 
-use lazy_static::lazy_static;
-use regex::Regex;
+use std::path::{Path, PathBuf};
+
+use marked_yaml::types::{MarkedScalarNode, Node};
 use thiserror::Error;
 
 use crate::{
+    annotation::{Annotation, AnnotationBlock, RawAnnotation},
+    dockerfile::{
+        variables::{self, Source},
+        BuildArg, CheckError, ImageReference, Variable,
+    },
     image::Image,
-    pattern::{self, Pattern},
+    location::Location,
+    pattern::Presets,
 };
+pub use environment::Environment;
 
 pub type ServiceName = String;
 
 #[derive(Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
 pub enum BuildContext<I, P, F> {
-    Image(Image, I),
+    Image(ImageReference, I),
     Folder(P, F),
 }
 
-pub fn parse(input: &str) -> Result<Vec<ServiceResult>, Error> {
+/// How a service is built from a Dockerfile, given by its `build` field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Build {
+    /// The build context, relative to the docker-compose file.
+    pub context: PathBuf,
+    /// The Dockerfile, relative to the `context`.
+    pub dockerfile: PathBuf,
+    /// The `args`, which override the defaults of the Dockerfile's `ARG`s.
+    pub args: Vec<BuildArg>,
+}
+
+impl Build {
+    /// A build from the `Dockerfile` in the `context` without arguments, as given by `build: <context>`.
+    pub fn from_context(context: impl Into<PathBuf>) -> Self {
+        Build {
+            context: context.into(),
+            dockerfile: PathBuf::from("Dockerfile"),
+            args: Vec::new(),
+        }
+    }
+
+    /// The path of the Dockerfile, given the folder containing the docker-compose file.
+    pub fn dockerfile_path(&self, compose_dir: &Path) -> PathBuf {
+        compose_dir.join(&self.context).join(&self.dockerfile)
+    }
+}
+
+/// The prefix of additional build contexts that refer to an image.
+const DOCKER_IMAGE_PREFIX: &str = "docker-image://";
+
+/// Finds the services of the docker-compose `file`, whose content is the `input`, without interpolating variables
+/// from an environment.
+///
+/// See [`parse_with_environment`].
+pub fn parse(file: &Path, input: &str, presets: &Presets) -> Result<Vec<ServiceResult>, Error> {
+    parse_with_environment(file, input, presets, &Environment::default())
+}
+
+/// Finds the services of the docker-compose `file`, whose content is the `input`.
+///
+/// A service with a `build` field results in its build, followed by the images of its `docker-image://` additional
+/// contexts. Otherwise, the service's `image` is checked. Variables in the images are interpolated from the
+/// `environment`, and a tag held in a variable of the `.env` file is located there.
+pub fn parse_with_environment(
+    file: &Path,
+    input: &str,
+    presets: &Presets,
+    environment: &Environment,
+) -> Result<Vec<ServiceResult>, Error> {
     use Error::*;
     let parsed = marked_yaml::parse_yaml(0, input)?;
     let root = parsed.as_mapping().unwrap(); // root is always a mapping
-    root.get_mapping("services")
-        .ok_or_else(|| {
-            if root.contains_key("services") {
-                MalformedDockerfile()
-            } else {
-                MissingField("services")
+    let services = root.get_mapping("services").ok_or_else(|| {
+        if root.contains_key("services") {
+            MalformedDockerfile()
+        } else {
+            MissingField("services")
+        }
+    })?;
+    let images = Images::new(file, input, presets, environment);
+
+    let mut results = Vec::new();
+    for (key, node) in services.iter() {
+        let service_name = key.as_str();
+        let service = node.as_mapping().ok_or(MalformedDockerfile())?;
+        if let Some(build_node) = service.get_node("build") {
+            let (build, contexts) = images.build(service_name, build_node)?;
+            results.push((service_name.to_string(), BuildContext::Folder(build, ())));
+            for (node, raw_image) in contexts {
+                results.extend(images.annotated(service_name, node, raw_image)?);
+            }
+        } else if let Some(image_node) = service.get_scalar("image") {
+            results.extend(images.annotated(service_name, image_node, image_node.as_str())?);
+        } else {
+            return Err(UnsupportedBuildContext {
+                service: service_name.to_string(),
+            });
+        }
+    }
+    Ok(results)
+}
+
+/// What the images in a docker-compose file are resolved against.
+struct Images<'a> {
+    file: &'a Path,
+    input: &'a str,
+    presets: &'a Presets,
+    block: AnnotationBlock<'a>,
+    environment: &'a Environment,
+    /// The variables of the `.env` file.
+    dotenv: Vec<environment::Variable<'a>>,
+}
+
+impl<'a> Images<'a> {
+    fn new(
+        file: &'a Path,
+        input: &'a str,
+        presets: &'a Presets,
+        environment: &'a Environment,
+    ) -> Self {
+        Images {
+            file,
+            input,
+            presets,
+            block: AnnotationBlock::find(input),
+            environment,
+            dotenv: environment
+                .dotenv
+                .as_ref()
+                .map_or_else(Vec::new, |dotenv| dotenv.variables()),
+        }
+    }
+
+    /// Parses the `raw_image` written in the `node` together with its annotation.
+    ///
+    /// An annotation of the image takes precedence over the annotation of the `.env` variable holding its tag. A
+    /// missing or invalid annotation is returned with the image. Returns `None` if the image is skipped.
+    fn annotated(
+        &self,
+        service_name: &str,
+        node: &MarkedScalarNode,
+        raw_image: &str,
+    ) -> Result<Option<ServiceResult>, Error> {
+        let (reference, definition) = self.resolve(node, raw_image)?;
+        let raw_annotation = match self.raw_annotation(node, &reference.image) {
+            Some(raw_annotation) => Some(raw_annotation.parse(self.presets)),
+            None => self.dotenv_annotation(definition).map(|raw_annotation| {
+                // The variable holds only part of the tag.
+                let variable = reference.variable.as_ref().unwrap();
+                raw_annotation.parse(self.presets).map(|annotation| {
+                    annotation.map(|annotation| Annotation {
+                        extractor: annotation
+                            .extractor
+                            .surrounded_by(&variable.prefix, &variable.suffix),
+                        ..annotation
+                    })
+                })
+            }),
+        };
+        let annotation = match raw_annotation {
+            Some(Ok(Some(annotation))) => Ok(annotation),
+            Some(Ok(None)) => return Ok(None),
+            Some(Err(error)) => Err(CheckError::from(error)),
+            None => Err(CheckError::UnspecifiedPattern),
+        };
+        Ok(Some((
+            service_name.to_string(),
+            BuildContext::Image(reference, annotation),
+        )))
+    }
+
+    /// Interpolates the variables in the `raw_image` written in the `node`.
+    ///
+    /// The tag is located where it is written: in the image itself, at the default of a variable, or in the `.env`
+    /// file, whose variable is returned as well. A tag held in a variable of the shell is located at the image.
+    fn resolve(
+        &self,
+        node: &MarkedScalarNode,
+        raw_image: &str,
+    ) -> Result<(ImageReference, Option<&environment::Variable<'a>>), Error> {
+        let location = self.locate(node, raw_image)?;
+        let segments =
+            variables::substitute(raw_image, |name| self.lookup(name).map(|(value, _)| value))
+                .map_err(|source| Error::Interpolation {
+                    image: raw_image.to_string(),
+                    source,
+                })?;
+        let substituted = segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect::<String>();
+        let image: Image = substituted
+            .parse()
+            .map_err(|_| Error::InvalidImage(raw_image.to_string()))?;
+
+        let (prefix, segment, suffix) = match variables::tag_segment(&segments, image.tag.len()) {
+            Some(tag_segment) => tag_segment,
+            None => return Ok((ImageReference::new(image, location), None)),
+        };
+        let (name, location, definition) = match &segment.source {
+            Source::Variable(name) => match self.lookup(name) {
+                Some((_, Some(definition))) => {
+                    let dotenv = self.environment.dotenv.as_ref().unwrap(); // The definition is in the file.
+                    let location =
+                        Location::new(&dotenv.file, &dotenv.input, definition.span.clone());
+                    (*name, location, Some(definition))
+                }
+                _ => return Ok((ImageReference::new(image, location), None)),
+            },
+            Source::Default { variable, span } => {
+                let start = location.span.start;
+                let location =
+                    Location::new(self.file, self.input, start + span.start..start + span.end);
+                (*variable, location, None)
             }
-        })?
-        .iter()
-        .map(|(key, node)| {
-            let service_name = key.as_str();
-            let service = node.as_mapping().ok_or(MalformedDockerfile())?;
-            let build_context = if let Some(path_node) = service.get_scalar("build") {
-                let raw_path = path_node.as_str();
-                BuildContext::Folder(raw_path.into(), ())
-            } else if let Some(image_node) = service.get_scalar("image") {
-                let raw_image = image_node.as_str();
-                let image = raw_image
-                    .parse()
-                    .map_err(|_| InvalidImage(raw_image.to_string()))?;
-                let image_line_number = image_node.span().start().unwrap().line();
-                let (_, preceding_line) = input
-                    .lines()
-                    .enumerate()
-                    .find(|(line_index, _)| *line_index == image_line_number - 2) // `line_index` starts at 0, `image_line_number` starts at 1.
-                    .unwrap(); // We are guaranteed to have at least the `service` line before this line.
-                let captures = PATTERN
-                    .captures(preceding_line)
-                    .ok_or_else(|| Error::MissingPattern(service_name.to_string()))?;
-                let raw_pattern = captures.name("pattern").unwrap().as_str(); // Group `pattern` is required for the regex to match.
-                let pattern =
-                    Pattern::parse(raw_pattern).map_err(|error| Error::InvalidPattern {
-                        service: service_name.to_string(),
-                        pattern: raw_pattern.to_string(),
-                        source: error,
-                    })?;
-                BuildContext::Image(image, pattern)
-            } else {
-                return Err(UnsupportedBuildContext {
-                    service: service_name.to_string(),
-                });
-            };
-            Ok((service_name.to_string(), build_context))
-        })
-        .collect()
+            Source::Literal(_) => return Ok((ImageReference::new(image, location), None)),
+        };
+        let reference = ImageReference {
+            image,
+            location,
+            variable: Some(Variable {
+                keyword: "variable".to_string(),
+                name: name.to_string(),
+                prefix,
+                suffix,
+            }),
+        };
+        Ok((reference, definition))
+    }
+
+    /// The value of the variable `name`, with its definition in the `.env` file unless the shell overrides it.
+    fn lookup(&self, name: &str) -> Option<(&'a str, Option<&environment::Variable<'a>>)> {
+        if let Some(value) = self.environment.shell.get(name) {
+            return Some((value.as_str(), None));
+        }
+        self.dotenv
+            .iter()
+            .rev()
+            .find(|variable| variable.name == name)
+            .map(|variable| (variable.value, Some(variable)))
+    }
+
+    /// The annotation of the `image` written in the `node`, either next to it or in the annotation block.
+    fn raw_annotation(&self, node: &MarkedScalarNode, image: &Image) -> Option<RawAnnotation<'a>> {
+        let start = node.span().start().unwrap();
+        find_annotation(self.input, start.line(), start.column())
+            .or_else(|| self.block.get(&image.name).cloned())
+    }
+
+    /// The annotation of the `definition` in the `.env` file, if any.
+    fn dotenv_annotation(
+        &self,
+        definition: Option<&environment::Variable<'a>>,
+    ) -> Option<RawAnnotation<'a>> {
+        let dotenv = self.environment.dotenv.as_ref()?;
+        let location = Location::new(&dotenv.file, &dotenv.input, definition?.span.clone());
+        find_annotation(&dotenv.input, location.line, location.column)
+    }
+
+    /// Parses the `build` field, either a path to the context or a mapping, together with the images of its
+    /// `docker-image://` additional contexts.
+    #[allow(clippy::type_complexity)]
+    fn build<'n>(
+        &self,
+        service_name: &str,
+        node: &'n Node,
+    ) -> Result<(Build, Vec<(&'n MarkedScalarNode, &'n str)>), Error> {
+        let unsupported = || Error::UnsupportedBuildContext {
+            service: service_name.to_string(),
+        };
+        if let Some(context) = node.as_scalar() {
+            return Ok((Build::from_context(context.as_str()), Vec::new()));
+        }
+        let build = node.as_mapping().ok_or_else(unsupported)?;
+
+        let mut args = Vec::new();
+        for (name, value) in entries(build.get_node("args")) {
+            args.push(BuildArg {
+                name: name.to_string(),
+                value: value.1.to_string(),
+                location: self.locate(value.0, value.1)?,
+            });
+        }
+        let contexts = entries(build.get_node("additional_contexts"))
+            .into_iter()
+            .filter_map(|(_, (node, value))| Some((node, value.strip_prefix(DOCKER_IMAGE_PREFIX)?)))
+            .collect();
+
+        Ok((
+            Build {
+                context: build
+                    .get_scalar("context")
+                    .map_or(".", |context| context.as_str())
+                    .into(),
+                dockerfile: build
+                    .get_scalar("dockerfile")
+                    .map_or("Dockerfile", |dockerfile| dockerfile.as_str())
+                    .into(),
+                args,
+            },
+            contexts,
+        ))
+    }
+
+    /// Locates the `value` written in the `node`.
+    fn locate(&self, node: &MarkedScalarNode, value: &str) -> Result<Location, Error> {
+        let start = node.span().start().unwrap();
+        // A value following a key in a list item, e.g., `NAME=value`, is the end of the item.
+        let offset = node.as_str().len() - value.len();
+        let item = locate_value(
+            self.file,
+            self.input,
+            start.line(),
+            start.column(),
+            node.as_str(),
+        )
+        .ok_or(Error::MalformedDockerfile())?;
+        let value_start = item.span.start + offset;
+        Ok(Location::new(
+            self.file,
+            self.input,
+            value_start..value_start + value.len(),
+        ))
+    }
 }
 
-type ServiceResult = (ServiceName, BuildContext<Pattern, PathBuf, ()>);
+/// The entries of a mapping, or of a list of `NAME=value` items, with the node holding each value.
+fn entries(node: Option<&Node>) -> Vec<(&str, (&MarkedScalarNode, &str))> {
+    match node {
+        Some(Node::Mapping(mapping)) => mapping
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = value.as_scalar()?;
+                Some((name.as_str(), (value, value.as_str())))
+            })
+            .collect(),
+        Some(Node::Sequence(items)) => items
+            .iter()
+            .filter_map(|item| {
+                let item = item.as_scalar()?;
+                let (name, value) = item.as_str().split_once('=')?;
+                Some((name, (item, value)))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Finds the images in the `image` fields and `docker-image://` additional contexts of services that are neither
+/// annotated nor covered by an annotation block, together with the locations of the fields.
+///
+/// An image whose tag is held in an annotated variable of the `.env` file counts as annotated.
+pub fn unannotated_images(
+    file: &Path,
+    input: &str,
+    presets: &Presets,
+    environment: &Environment,
+) -> Result<Vec<(ImageReference, Location)>, Error> {
+    use Error::*;
+    let parsed = marked_yaml::parse_yaml(0, input)?;
+    let root = parsed.as_mapping().unwrap(); // root is always a mapping
+    let services = root
+        .get_mapping("services")
+        .ok_or(MissingField("services"))?;
+    let images = Images::new(file, input, presets, environment);
+
+    let mut unannotated = Vec::new();
+    for (key, node) in services.iter() {
+        let service = match node.as_mapping() {
+            Some(service) => service,
+            None => continue,
+        };
+        let fields = if let Some(build_node) = service.get_node("build") {
+            images.build(key.as_str(), build_node)?.1
+        } else if let Some(image_node) = service.get_scalar("image") {
+            vec![(image_node, image_node.as_str())]
+        } else {
+            continue;
+        };
+        for (node, raw_image) in fields {
+            let (reference, definition) = images.resolve(node, raw_image)?;
+            if images.raw_annotation(node, &reference.image).is_some()
+                || images.dotenv_annotation(definition).is_some()
+            {
+                continue;
+            }
+            let location = images.locate(node, raw_image)?;
+            unannotated.push((reference, location));
+        }
+    }
+    Ok(unannotated)
+}
+
+/// Locates the `value` at or after the `line` and `column`, e.g., after a leading quote.
+fn locate_value(
+    file: &Path,
+    input: &str,
+    line: usize,
+    column: usize,
+    value: &str,
+) -> Option<Location> {
+    let start = Location::at(file, input, line, column, 0)?.span.start;
+    let start = start + input[start..].find(value)?;
+    Some(Location::new(file, input, start..start + value.len()))
+}
+
+/// Finds the annotation of the value starting at the `line` and `column`, both starting at 1.
+///
+/// The annotation is either a trailing comment on the same line or the last annotation among the
+/// comments and empty lines directly above it.
+fn find_annotation(input: &str, line: usize, column: usize) -> Option<RawAnnotation<'_>> {
+    let lines = input.lines().collect::<Vec<_>>();
+    let value_line = lines.get(line - 1)?;
+    let value_start = value_line
+        .char_indices()
+        .nth(column - 1)
+        .map_or(value_line.len(), |(index, _)| index);
+    let trailing = value_line[value_start..]
+        .find('#')
+        .and_then(|hash| RawAnnotation::from_comment(&value_line[value_start + hash + 1..], line));
+
+    trailing.or_else(|| {
+        let comments = lines[..line - 1]
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, line)| (index, line.trim_start()))
+            .take_while(|(_, line)| line.is_empty() || line.starts_with('#'))
+            .filter_map(|(index, line)| Some((line.strip_prefix('#')?, index + 1)))
+            .collect::<Vec<_>>();
+        // The comments were collected from the bottom up.
+        RawAnnotation::last_of(comments.into_iter().rev())
+    })
+}
+
+/// A service with the annotated image it uses or the build of its image.
+pub type ServiceResult = (
+    ServiceName,
+    BuildContext<Result<Annotation, CheckError>, Build, ()>,
+);
 
 #[derive(Debug, Error, PartialEq)]
 pub enum Error {
@@ -80,28 +457,36 @@ pub enum Error {
     MissingField(&'static str),
     #[error("The image definition `{0}` is invalid")]
     InvalidImage(String),
-    #[error("No build context was found for service `{service}` (Only the `build` and `image` fields containing strings are supported)")]
-    UnsupportedBuildContext { service: String },
-    #[error("Failed to find pattern for service `{0}` in the line before the `image` field")]
-    MissingPattern(String),
-    #[error("The pattern `{pattern}` for service `{service}` is invalid")]
-    InvalidPattern {
-        service: String,
-        pattern: String,
+    #[error("Failed to interpolate the variables in the image definition `{image}`")]
+    Interpolation {
+        image: String,
         #[source]
-        source: pattern::Error,
+        source: variables::Error,
     },
-}
-
-lazy_static! {
-    static ref PATTERN: Regex =
-        Regex::new(r#"#\s*uptag\s+--pattern\s+"(?P<pattern>[^"]*)""#).unwrap();
+    #[error("No build context was found for service `{service}` (Only `image` fields containing strings and `build` fields containing strings or mappings are supported)")]
+    UnsupportedBuildContext { service: String },
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    use crate::version::extractor::VersionExtractor;
+    use crate::MinAge;
+
+    const FILE: &str = "docker-compose.yml";
+
+    fn parse_all(input: &str) -> Result<Vec<ServiceResult>, Error> {
+        parse(Path::new(FILE), input, &Presets::builtin())
+    }
+
+    /// The reference to the first occurrence of the `raw` image in the `input`.
+    fn reference(input: &str, raw: &str) -> ImageReference {
+        let start = input.find(raw).unwrap();
+        let location = Location::new(Path::new(FILE), input, start..start + raw.len());
+        ImageReference::new(raw.parse().unwrap(), location)
+    }
+
     #[test]
     fn parses_services() {
         let input = r#"
@@ -114,29 +499,340 @@ services:
         build: ./alpine
         "#;
         assert_eq!(
-            parse(input),
+            parse_all(input),
             Ok(vec![
                 (
                     "ubuntu".to_string(),
                     BuildContext::Image(
-                        "ubuntu:18.04".parse().unwrap(),
-                        Pattern::parse("<!>.<>").unwrap()
+                        reference(input, "ubuntu:18.04"),
+                        Ok(Annotation::new(VersionExtractor::parse("<!>.<>").unwrap()))
                     )
                 ),
                 (
                     "alpine".to_string(),
-                    BuildContext::Folder("./alpine".into(), ())
+                    BuildContext::Folder(Build::from_context("./alpine"), ())
                 )
             ])
         )
     }
 
+    #[test]
+    fn parses_regex() {
+        let input = r#"
+services:
+    java:
+        # uptag --regex "(?P<breaking>\d+)u(\d+)"
+        image: openjdk:8u292
+        "#;
+        assert_eq!(
+            parse_all(input),
+            Ok(vec![(
+                "java".to_string(),
+                BuildContext::Image(
+                    reference(input, "openjdk:8u292"),
+                    Ok(Annotation::new(
+                        VersionExtractor::from_regex(r"(?P<breaking>\d+)u(\d+)").unwrap()
+                    ))
+                )
+            )])
+        )
+    }
+
+    #[test]
+    fn parses_options() {
+        let input = r#"
+services:
+    ubuntu:
+        # uptag --pattern "<!>.<>" --min-age 7d --ignore "18.10" --note "LTS only"
+        image: ubuntu:18.04
+        "#;
+        assert_eq!(
+            parse_all(input),
+            Ok(vec![(
+                "ubuntu".to_string(),
+                BuildContext::Image(
+                    reference(input, "ubuntu:18.04"),
+                    Ok(Annotation {
+                        extractor: VersionExtractor::parse("<!>.<>").unwrap(),
+                        min_age: Some(MinAge::days(7)),
+                        ignore: vec!["18.10".to_string()],
+                        image: None,
+                        search_limit: None,
+                        note: Some("LTS only".to_string()),
+                    })
+                )
+            )])
+        )
+    }
+
+    #[test]
+    fn finds_annotations_in_flexible_places() {
+        let input = r#"
+# uptag images:
+#   alpine: --pattern "<!>.<>.<>"
+services:
+    ubuntu:
+        image: ubuntu:18.04 # uptag --pattern "<!>.<>"
+    node:
+        # uptag --pattern "<!>.<>.<>"
+
+        # The frontend's runtime.
+        image: node:18.17.0
+    alpine:
+        image: alpine:3.18.2
+        "#;
+        let annotation = |pattern| Ok(Annotation::new(VersionExtractor::parse(pattern).unwrap()));
+        assert_eq!(
+            parse_all(input),
+            Ok(vec![
+                (
+                    "ubuntu".to_string(),
+                    BuildContext::Image(reference(input, "ubuntu:18.04"), annotation("<!>.<>"))
+                ),
+                (
+                    "node".to_string(),
+                    BuildContext::Image(reference(input, "node:18.17.0"), annotation("<!>.<>.<>"))
+                ),
+                (
+                    "alpine".to_string(),
+                    BuildContext::Image(reference(input, "alpine:3.18.2"), annotation("<!>.<>.<>"))
+                ),
+            ])
+        )
+    }
+
+    #[test]
+    fn locates_images() {
+        let input =
+            "services:\n  ubuntu:\n    # uptag --pattern \"<!>.<>\"\n    image: \"ubuntu:18.04\"\n";
+        let services = parse_all(input).unwrap();
+        match &services[0].1 {
+            BuildContext::Image(reference, _) => {
+                assert_eq!(reference.location.to_string(), "docker-compose.yml:4:13");
+                assert_eq!(&input[reference.location.span.clone()], "ubuntu:18.04");
+            }
+            BuildContext::Folder(_, _) => panic!("Expected an image"),
+        }
+    }
+
+    #[test]
+    fn parses_long_build_syntax() {
+        let input = r#"
+services:
+    app:
+        build:
+            context: ./app
+            dockerfile: docker/app.Dockerfile
+            args:
+                NODE_VERSION: "18.17.0"
+            additional_contexts:
+                # uptag --pattern "<!>.<>"
+                base: docker-image://alpine:3.18
+                src: ./src
+    worker:
+        build:
+            args:
+                - NODE_VERSION=20.5.1
+                - DEBUG
+        "#;
+        let build_arg = |value: &str| {
+            let start = input.find(value).unwrap();
+            BuildArg {
+                name: "NODE_VERSION".to_string(),
+                value: value.to_string(),
+                location: Location::new(Path::new(FILE), input, start..start + value.len()),
+            }
+        };
+        assert_eq!(
+            parse_all(input),
+            Ok(vec![
+                (
+                    "app".to_string(),
+                    BuildContext::Folder(
+                        Build {
+                            context: "./app".into(),
+                            dockerfile: "docker/app.Dockerfile".into(),
+                            args: vec![build_arg("18.17.0")],
+                        },
+                        ()
+                    )
+                ),
+                (
+                    "app".to_string(),
+                    BuildContext::Image(
+                        reference(input, "alpine:3.18"),
+                        Ok(Annotation::new(VersionExtractor::parse("<!>.<>").unwrap()))
+                    )
+                ),
+                (
+                    "worker".to_string(),
+                    BuildContext::Folder(
+                        Build {
+                            context: ".".into(),
+                            dockerfile: "Dockerfile".into(),
+                            args: vec![build_arg("20.5.1")],
+                        },
+                        ()
+                    )
+                ),
+            ])
+        );
+        assert_eq!(
+            Build::from_context("./alpine").dockerfile_path(Path::new("compose")),
+            Path::new("compose/./alpine/Dockerfile")
+        );
+    }
+
+    #[test]
+    fn interpolates_variables() {
+        let input = r#"
+services:
+    db:
+        # uptag --pattern "<!>.<>"
+        image: postgres:${PG_VERSION:-15.3}
+    node:
+        image: node:${NODE_VERSION}-slim
+    redis:
+        # uptag --pattern "<!>.<>"
+        image: ${NAMESPACE}/redis:${REDIS_VERSION?}
+        "#;
+        let dotenv = "# uptag --pattern \"<!>.<>.<>\"\nNODE_VERSION=18.17.0\nREDIS_VERSION=7.0\n";
+        let environment = Environment {
+            shell: vec![("NAMESPACE", "bitnami"), ("REDIS_VERSION", "7.2")]
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            dotenv: Some(environment::Dotenv {
+                file: PathBuf::from(".env"),
+                input: dotenv.to_string(),
+            }),
+        };
+        let services =
+            parse_with_environment(Path::new(FILE), input, &Presets::builtin(), &environment)
+                .unwrap();
+        let images = services
+            .iter()
+            .map(|(_, build_context)| match build_context {
+                BuildContext::Image(reference, annotation) => (reference.to_string(), annotation),
+                BuildContext::Folder(_, _) => panic!("Expected an image"),
+            })
+            .collect::<Vec<_>>();
+
+        let annotation = |pattern| Ok(Annotation::new(VersionExtractor::parse(pattern).unwrap()));
+        assert_eq!(
+            images,
+            vec![
+                (
+                    "postgres:15.3 (variable PG_VERSION at docker-compose.yml:5:39)".to_string(),
+                    &annotation("<!>.<>")
+                ),
+                (
+                    "node:18.17.0-slim (variable NODE_VERSION at .env:2:14)".to_string(),
+                    &Ok(Annotation::new(
+                        VersionExtractor::parse("<!>.<>.<>")
+                            .unwrap()
+                            .surrounded_by("", "-slim")
+                    ))
+                ),
+                (
+                    "bitnami/redis:7.2 (docker-compose.yml:10:16)".to_string(),
+                    &annotation("<!>.<>")
+                ),
+            ]
+        );
+
+        // `$$` is a literal `$`.
+        let input = "services:\n  web:\n    image: nginx:$${NOT} # uptag --pattern \"<>\"\n";
+        match &parse_all(input).unwrap()[0].1 {
+            BuildContext::Image(reference, _) => assert_eq!(reference.image.tag, "${NOT}"),
+            BuildContext::Folder(_, _) => panic!("Expected an image"),
+        }
+
+        let input = "services:\n  db:\n    image: postgres:${PG_VERSION:?Set the version}\n";
+        assert_eq!(
+            parse_all(input),
+            Err(Error::Interpolation {
+                image: "postgres:${PG_VERSION:?Set the version}".to_string(),
+                source: variables::Error::Required {
+                    name: "PG_VERSION".to_string(),
+                    message: "Set the version".to_string()
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn reports_missing_and_invalid_patterns_per_service() {
+        let input = r#"
+services:
+    # uptag --pattern "<!>.<>"
+    ubuntu:
+        image: ubuntu:18.04
+    app:
+        build:
+            additional_contexts:
+                base: docker-image://alpine:3.18
+    node:
+        # uptag --pattern "<!>.<"
+        image: node:18.17.0
+    # uptag --pattern "<!>.<>.<>"
+    python:
+        image: python:3.11.4
+        "#;
+        let services = parse_all(input).unwrap();
+        let images = services
+            .iter()
+            .filter_map(|(service, build_context)| match build_context {
+                BuildContext::Image(reference, annotation) => Some((
+                    service.as_str(),
+                    reference.location.to_string(),
+                    annotation.as_ref().map_err(|error| error.to_string()),
+                )),
+                BuildContext::Folder(_, _) => None,
+            })
+            .collect::<Vec<_>>();
+
+        let missing = Err(CheckError::UnspecifiedPattern.to_string());
+        assert_eq!(
+            images,
+            vec![
+                (
+                    "ubuntu",
+                    "docker-compose.yml:5:16".to_string(),
+                    missing.clone()
+                ),
+                (
+                    "app",
+                    "docker-compose.yml:9:38".to_string(),
+                    missing.clone()
+                ),
+                (
+                    "node",
+                    "docker-compose.yml:12:16".to_string(),
+                    Err("The version pattern `<!>.<` is invalid".to_string())
+                ),
+                ("python", "docker-compose.yml:15:16".to_string(), missing),
+            ]
+        )
+    }
+
+    #[test]
+    fn skips_services() {
+        let input = r#"
+services:
+    ubuntu:
+        # uptag --skip
+        image: ubuntu:18.04
+        "#;
+        assert_eq!(parse_all(input), Ok(vec![]))
+    }
+
     #[test]
     fn fails_when_services_is_missing() {
         let input = r#"
 no: services
                 "#;
-        assert_eq!(parse(input), Err(Error::MissingField("services")))
+        assert_eq!(parse_all(input), Err(Error::MissingField("services")))
     }
 
     #[test]
@@ -146,7 +842,7 @@ services:
     - ubuntu
     - alpine:
                 "#;
-        assert_eq!(parse(input), Err(Error::MalformedDockerfile()))
+        assert_eq!(parse_all(input), Err(Error::MalformedDockerfile()))
     }
 
     #[test]
@@ -158,7 +854,7 @@ services:
         image: "invalid/image/definition"
         "#;
         assert_eq!(
-            parse(input),
+            parse_all(input),
             Err(Error::InvalidImage("invalid/image/definition".to_string()))
         )
     }
@@ -169,10 +865,10 @@ services:
 services:
     alpine:
         build:
-            context: unsupported
+            - unsupported
         "#;
         assert_eq!(
-            parse(input),
+            parse_all(input),
             Err(Error::UnsupportedBuildContext {
                 service: "alpine".to_string()
             })
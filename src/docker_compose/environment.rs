@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::dockerfile::instructions::Span;
+
+/// The variables that a docker-compose file is interpolated with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Environment {
+    /// The variables of the shell, which take precedence over the `.env` file.
+    pub shell: HashMap<String, String>,
+    /// The `.env` file of the project, if there is one.
+    pub dotenv: Option<Dotenv>,
+}
+
+impl Environment {
+    /// The variables of the current process and the `.env` file in the `project_dir`, i.e., the folder of the
+    /// docker-compose file.
+    pub fn load(project_dir: &Path) -> io::Result<Self> {
+        let file = project_dir.join(".env");
+        let dotenv = match fs::read_to_string(&file) {
+            Ok(input) => Some(Dotenv { file, input }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error),
+        };
+        Ok(Environment {
+            shell: std::env::vars().collect(),
+            dotenv,
+        })
+    }
+
+    /// The same environment with the `.env` file's content replaced by the `input`.
+    pub fn with_dotenv(&self, input: String) -> Self {
+        Environment {
+            shell: self.shell.clone(),
+            dotenv: self.dotenv.as_ref().map(|dotenv| Dotenv {
+                file: dotenv.file.clone(),
+                input,
+            }),
+        }
+    }
+}
+
+/// A `.env` file and its content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dotenv {
+    pub file: PathBuf,
+    pub input: String,
+}
+
+impl Dotenv {
+    /// The variables defined in the file, where later definitions override earlier ones.
+    pub fn variables(&self) -> Vec<Variable<'_>> {
+        variables(&self.input)
+    }
+}
+
+/// A variable defined in a `.env` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable<'a> {
+    pub name: &'a str,
+    /// The value without surrounding quotes or trailing comment.
+    pub value: &'a str,
+    pub span: Span,
+}
+
+/// Finds the variables defined by `NAME=value` lines, optionally preceded by `export`.
+///
+/// Values may be quoted. Unquoted values end at a comment, which starts with ` #`.
+pub fn variables(input: &str) -> Vec<Variable<'_>> {
+    let mut variables = Vec::new();
+    let mut line_start = 0;
+    for line in input.split_inclusive('\n') {
+        let start = line_start;
+        line_start += line.len();
+
+        let definition = line.trim_start();
+        let definition = definition
+            .strip_prefix("export ")
+            .unwrap_or(definition)
+            .trim_start();
+        let (name, raw_value) = match definition.split_once('=') {
+            Some(split) if !definition.starts_with('#') => split,
+            _ => continue,
+        };
+        let name = name.trim_end();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            continue;
+        }
+
+        let raw_value = raw_value.trim_start();
+        let value_start = start + (line.len() - raw_value.len());
+        let quote = raw_value.chars().next().filter(|c| *c == '"' || *c == '\'');
+        let (value_start, value) = match quote {
+            Some(quote) => match raw_value[1..].find(quote) {
+                Some(end) => (value_start + 1, &raw_value[1..end + 1]),
+                None => continue,
+            },
+            None => {
+                let end = raw_value
+                    .find(" #")
+                    .or_else(|| raw_value.find("\t#"))
+                    .unwrap_or(raw_value.len());
+                (value_start, raw_value[..end].trim_end())
+            }
+        };
+        variables.push(Variable {
+            name,
+            value,
+            span: value_start..value_start + value.len(),
+        });
+    }
+    variables
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_variables() {
+        let input = "# The database.\nPG_VERSION=15.3 # uptag --pattern \"<!>.<>\"\n\nexport NODE_VERSION = \"18.17.0\"\nEMPTY=\r\nINVALID\n";
+        let variables = variables(input);
+
+        assert_eq!(
+            variables
+                .iter()
+                .map(|variable| (variable.name, variable.value))
+                .collect::<Vec<_>>(),
+            vec![
+                ("PG_VERSION", "15.3"),
+                ("NODE_VERSION", "18.17.0"),
+                ("EMPTY", "")
+            ]
+        );
+        for variable in &variables {
+            assert_eq!(&input[variable.span.clone()], variable.value);
+        }
+    }
+}
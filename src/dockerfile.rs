@@ -1,24 +1,403 @@
+pub mod instructions;
+pub mod variables;
+
+use std::fmt;
+use std::path::Path;
+
 use thiserror::Error;
 
-use crate::image::Image;
+use crate::annotation::{self, Annotation, AnnotationBlock, RawAnnotation};
+use crate::image::{Image, ImageName};
+use crate::location::Location;
 use crate::pattern;
-use crate::pattern::Pattern;
-use matches::Matches;
-
-pub fn parse(input: &str) -> impl Iterator<Item = (Image, Result<Pattern, CheckError>)> + '_ {
-    Matches::iter(input).map(|matches| {
-        let image = matches.image();
-        let pattern = matches
-            .pattern()
-            .ok_or(CheckError::UnspecifiedPattern)
-            .and_then(|m| {
-                Pattern::parse(m.as_str()).map_err(|error| CheckError::InvalidPattern {
-                    pattern: m.as_str().to_string(),
-                    source: error,
-                })
-            });
-        (image, pattern)
-    })
+use crate::pattern::Presets;
+use crate::version::extractor;
+use instructions::{Base, Comment, FromInstruction, Span, Word};
+use variables::Declaration;
+
+/// An image found in a Dockerfile or docker-compose file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageReference {
+    pub image: Image,
+    /// Where the tag is written: the image reference itself, or the value of the `variable`.
+    pub location: Location,
+    /// The variable holding the tag, if the tag is not written out in the image reference.
+    pub variable: Option<Variable>,
+}
+
+impl ImageReference {
+    pub fn new(image: Image, location: Location) -> Self {
+        ImageReference {
+            image,
+            location,
+            variable: None,
+        }
+    }
+
+    /// Describes where the tag is written, e.g., `Dockerfile:3:6` or `ARG NODE_VERSION at Dockerfile:2:18`.
+    pub fn origin(&self) -> String {
+        match &self.variable {
+            Some(variable) => format!("{} at {}", variable, self.location),
+            None => self.location.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ImageReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.image, self.origin())
+    }
+}
+
+/// A variable of an `ARG` or `ENV` instruction, or of a docker-compose file, that holds (part of) an image's tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    /// Either `ARG` or `ENV`, or `variable` for a docker-compose file.
+    pub keyword: String,
+    pub name: String,
+    /// The part of the tag before the variable's value.
+    pub prefix: String,
+    /// The part of the tag after the variable's value.
+    pub suffix: String,
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.keyword, self.name)
+    }
+}
+
+/// A value for an `ARG` given from outside the Dockerfile, such as the `args` of a docker-compose build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildArg {
+    pub name: String,
+    pub value: String,
+    /// Where the value is written.
+    pub location: Location,
+}
+
+/// Finds the images in `FROM` instructions and the annotations that precede them.
+///
+/// Images used by `COPY --from` and `RUN --mount=from` are checked as well, and annotated like `FROM` instructions.
+///
+/// Variables in `FROM` instructions are substituted with the defaults of `ARG`s declared before the first `FROM`.
+/// If the tag then contains a single variable, the variable's annotation applies to it.
+/// Annotated `ARG` and `ENV` instructions that name an image with `--image` are checked as well.
+///
+/// Build stages, `scratch` and images that cannot be checked, e.g., because they are pinned by digest, are skipped.
+pub fn parse<'a>(
+    file: &'a Path,
+    input: &'a str,
+    presets: &'a Presets,
+) -> impl Iterator<Item = (ImageReference, Result<Annotation, CheckError>)> + 'a {
+    parse_with_args(file, input, presets, &[])
+}
+
+/// Like `parse`, but with the `build_args` overriding the defaults of `ARG`s.
+///
+/// If a build argument holds an image's tag, the image's location points at the build argument's value.
+pub fn parse_with_args<'a>(
+    file: &'a Path,
+    input: &'a str,
+    presets: &'a Presets,
+    build_args: &'a [BuildArg],
+) -> impl Iterator<Item = (ImageReference, Result<Annotation, CheckError>)> + 'a {
+    let mut context = Context {
+        file,
+        input,
+        presets,
+        block: AnnotationBlock::find(input),
+        build_args,
+        global_args: Vec::new(),
+    };
+    let mut references = Vec::new();
+    let mut stages = Vec::new();
+    let mut is_global = true;
+
+    for instruction in instructions::tokenize(input) {
+        if instruction.is("ARG") || instruction.is("ENV") {
+            let keyword = instruction.keyword.text.to_uppercase();
+            let declarations = variables::declarations(input, &instruction);
+            for (index, declaration) in declarations.into_iter().enumerate() {
+                // An annotation applies to the first variable of an instruction.
+                let comments = if index == 0 {
+                    instruction.comments.clone()
+                } else {
+                    Vec::new()
+                };
+                let mut argument = Argument {
+                    keyword: keyword.clone(),
+                    declaration,
+                    comments,
+                    names_image: false,
+                };
+
+                if let Some(reference) = argument.image_reference(&context) {
+                    references.push(reference);
+                    argument.names_image = true;
+                }
+                if is_global && argument.keyword == "ARG" {
+                    context.global_args.push(argument);
+                }
+            }
+        } else if instruction.is("FROM") {
+            is_global = false;
+            let from = match FromInstruction::parse(instruction, &stages) {
+                Some(from) => from,
+                None => continue,
+            };
+            if let Some(stage) = &from.stage {
+                stages.push(stage.text);
+            }
+
+            references.extend(context.check_reference(&from.image, from.base, &from.comments));
+        } else {
+            for source in instructions::image_sources(&instruction) {
+                let base = instructions::parse_reference(source.text, &stages);
+                references.extend(context.check_reference(&source, base, &instruction.comments));
+            }
+        }
+    }
+
+    references.into_iter()
+}
+
+/// Checks that the current tag of the `image` matches the pattern of its `annotation`, without accessing the network.
+pub fn validate(image: &Image, annotation: &Annotation) -> Result<(), CheckError> {
+    annotation
+        .extractor
+        .extract_from(&image.tag)
+        .map(|_| ())
+        .ok_or_else(|| CheckError::InvalidCurrentTag {
+            tag: image.tag.clone(),
+            pattern: annotation.extractor.to_string(),
+        })
+}
+
+/// What the images in a Dockerfile are resolved against.
+struct Context<'a> {
+    file: &'a Path,
+    input: &'a str,
+    presets: &'a Presets,
+    block: AnnotationBlock<'a>,
+    build_args: &'a [BuildArg],
+    /// The `ARG`s declared before the first `FROM`.
+    global_args: Vec<Argument<'a>>,
+}
+
+impl<'a> Context<'a> {
+    /// Resolves the image referred to by the `word`, which is skipped if it cannot be checked.
+    fn check_reference(
+        &self,
+        word: &Word,
+        base: Base,
+        comments: &[Comment],
+    ) -> Option<(ImageReference, Result<Annotation, CheckError>)> {
+        match base {
+            Base::Image(image) => {
+                let annotation = self.annotation(comments, &image)?;
+                let location = self.locate(word.span.clone());
+                Some((ImageReference::new(image, location), annotation))
+            }
+            Base::Variables => self.resolve_variables(word, comments),
+            Base::Stage(_) | Base::Scratch | Base::Unsupported => None,
+        }
+    }
+
+    /// Parses the last annotation among the `comments`, falling back to the annotation block's entry for the `image`.
+    ///
+    /// Returns `None` if the image is skipped.
+    fn annotation(
+        &self,
+        comments: &[Comment],
+        image: &Image,
+    ) -> Option<Result<Annotation, CheckError>> {
+        let raw = match last_annotation(self.input, comments)
+            .or_else(|| self.block.get(&image.name).cloned())
+        {
+            Some(raw) => raw,
+            None => return Some(Err(CheckError::UnspecifiedPattern)),
+        };
+        raw.parse(self.presets)
+            .map_err(CheckError::from)
+            .transpose()
+    }
+
+    fn locate(&self, span: Span) -> Location {
+        Location::new(self.file, self.input, span)
+    }
+
+    /// The value of the `argument` and where it is written, which is overridden by a build argument of the same name.
+    fn value_of(&self, argument: &Argument<'a>) -> Option<(&'a str, Location)> {
+        let build_arg = self
+            .build_args
+            .iter()
+            .find(|build_arg| build_arg.name == argument.declaration.name);
+        match build_arg {
+            Some(build_arg) if argument.keyword == "ARG" => {
+                Some((build_arg.value.as_str(), build_arg.location.clone()))
+            }
+            _ => {
+                let value = argument.declaration.value.as_ref()?;
+                Some((value.text, self.locate(value.span.clone())))
+            }
+        }
+    }
+
+    fn find_argument(&self, name: &str) -> Option<&Argument<'a>> {
+        self.global_args
+            .iter()
+            .rev()
+            .find(|argument| argument.declaration.name == name)
+    }
+
+    /// Substitutes the global `ARG`s into the image reference in `word`.
+    ///
+    /// A variable that is not set results in an error for the image as written. Returns `None` if the image is skipped
+    /// or the result is not an image that can be checked.
+    fn resolve_variables(
+        &self,
+        word: &Word,
+        comments: &[Comment],
+    ) -> Option<(ImageReference, Result<Annotation, CheckError>)> {
+        let segments = match variables::substitute(word.text, |name| {
+            self.find_argument(name)
+                .and_then(|argument| self.value_of(argument))
+                .map(|(value, _)| value)
+        }) {
+            Ok(segments) => segments,
+            Err(error) => {
+                let image = instructions::unresolved_image(word.text);
+                // The annotation may still skip the image.
+                let _ = self.annotation(comments, &image)?;
+                let reference = ImageReference::new(image, self.locate(word.span.clone()));
+                return Some((reference, Err(CheckError::UnresolvedVariable(error))));
+            }
+        };
+        let substituted = segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect::<String>();
+        let image = match instructions::parse_base(&substituted) {
+            Base::Image(image) => image,
+            _ => return None,
+        };
+
+        // The variable has to be defined, since the substitution succeeded.
+        let tag_variable = variables::tag_segment(&segments, image.tag.len()).and_then(
+            |(prefix, segment, suffix)| {
+                let argument = self.find_argument(segment.variable()?)?;
+                Some((argument, prefix, suffix))
+            },
+        );
+        // The variable's tag is already checked as the image named by its annotation.
+        if tag_variable
+            .as_ref()
+            .is_some_and(|(argument, _, _)| argument.names_image)
+        {
+            return None;
+        }
+
+        let has_annotation = last_annotation(self.input, comments).is_some();
+        let argument_annotation = tag_variable.as_ref().filter(|_| !has_annotation).and_then(
+            |(argument, prefix, suffix)| {
+                let raw = last_annotation(self.input, &argument.comments)?;
+                Some(raw.parse(self.presets).map(|annotation| {
+                    annotation.map(|annotation| Annotation {
+                        extractor: annotation.extractor.surrounded_by(prefix, suffix),
+                        ..annotation
+                    })
+                }))
+            },
+        );
+        let annotation = match argument_annotation {
+            Some(annotation) => annotation.map_err(CheckError::from).transpose()?,
+            None => self.annotation(comments, &image)?,
+        };
+        let reference = match tag_variable {
+            Some((argument, prefix, suffix)) => {
+                argument.reference(self, image, &prefix, &suffix)?
+            }
+            None => ImageReference::new(image, self.locate(word.span.clone())),
+        };
+
+        Some((reference, annotation))
+    }
+}
+
+/// A variable declared by an `ARG` or `ENV` instruction.
+struct Argument<'a> {
+    keyword: String,
+    declaration: Declaration<'a>,
+    /// The comments preceding the instruction, if the variable is the first of the instruction.
+    comments: Vec<Comment<'a>>,
+    /// Whether the annotation names an image with `--image`, which is checked on its own.
+    names_image: bool,
+}
+
+impl<'a> Argument<'a> {
+    /// A reference to the `image`, whose tag is held in this variable's value between `prefix` and `suffix`.
+    fn reference(
+        &self,
+        context: &Context,
+        image: Image,
+        prefix: &str,
+        suffix: &str,
+    ) -> Option<ImageReference> {
+        let (_, location) = context.value_of(self)?;
+        Some(ImageReference {
+            image,
+            location,
+            variable: Some(Variable {
+                keyword: self.keyword.clone(),
+                name: self.declaration.name.to_string(),
+                prefix: prefix.to_string(),
+                suffix: suffix.to_string(),
+            }),
+        })
+    }
+
+    /// The image named by the annotation's `--image` option, tagged with the variable's value.
+    ///
+    /// An invalid annotation results in an error for the image, as long as it names one.
+    fn image_reference(
+        &self,
+        context: &Context,
+    ) -> Option<(ImageReference, Result<Annotation, CheckError>)> {
+        let raw = last_annotation(context.input, &self.comments)?;
+        let (name, annotation) = match raw.parse(context.presets) {
+            Ok(annotation) => {
+                let annotation = annotation?;
+                (annotation.image.clone()?, Ok(annotation))
+            }
+            Err(error) => {
+                let name = raw.image()?;
+                let name = name.parse().unwrap_or_else(|_| ImageName::new(None, name));
+                (name, Err(CheckError::from(error)))
+            }
+        };
+        let (value, _) = context.value_of(self)?;
+        let image = Image {
+            name,
+            tag: value.to_string(),
+        };
+        let reference = self.reference(context, image, "", "")?;
+        Some((reference, annotation))
+    }
+}
+
+/// Reads the last annotation among the `comments`.
+fn last_annotation<'c>(input: &str, comments: &[Comment<'c>]) -> Option<RawAnnotation<'c>> {
+    RawAnnotation::last_of(
+        comments
+            .iter()
+            .map(|comment| (comment.text, line_of(input, comment.span.start))),
+    )
+}
+
+/// The line of the byte at `offset`, starting at 1.
+fn line_of(input: &str, offset: usize) -> usize {
+    input[..offset].matches('\n').count() + 1
 }
 
 type Tag = String;
@@ -35,179 +414,436 @@ pub enum CheckError {
         #[source]
         source: pattern::Error,
     },
+    #[error("The version regex `{regex}` is invalid")]
+    InvalidRegex {
+        regex: String,
+        #[source]
+        source: extractor::RegexError,
+    },
+    #[error("The annotation's options are invalid")]
+    InvalidOption(#[source] annotation::Error),
+    #[error("Failed to substitute the variables, which have to be set by an `ARG` before the first `FROM`")]
+    UnresolvedVariable(#[source] variables::Error),
+}
+
+impl From<annotation::Error> for CheckError {
+    fn from(error: annotation::Error) -> Self {
+        match error {
+            annotation::Error::InvalidPattern { pattern, source } => {
+                CheckError::InvalidPattern { pattern, source }
+            }
+            annotation::Error::InvalidRegex { regex, source } => {
+                CheckError::InvalidRegex { regex, source }
+            }
+            error => CheckError::InvalidOption(error),
+        }
+    }
 }
 
-pub mod matches {
-    use lazy_static::lazy_static;
-    use regex::Regex;
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    use crate::image::{Image, ImageName};
-    use crate::pattern;
-    use crate::version::extractor::{Tagged, VersionExtractor};
+    use crate::image::ImageName;
+    use crate::version::extractor::VersionExtractor;
+    use crate::MinAge;
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    pub struct Matches<'t> {
-        all: regex::Match<'t>,
-        user: Option<regex::Match<'t>>,
-        image: regex::Match<'t>,
-        tag: regex::Match<'t>,
-        pattern: Option<regex::Match<'t>>,
+    fn parse_references(dockerfile: &str) -> Vec<(ImageReference, Result<Annotation, CheckError>)> {
+        parse(Path::new("Dockerfile"), dockerfile, &Presets::builtin()).collect()
     }
 
-    lazy_static! {
-        static ref STATEMENT: Regex = Regex::new(
-            r#"(#\s*uptag\s+--pattern\s+"(?P<pattern>[^"]*)"\s*\n[\s\n]*)?\s*FROM\s*((?P<user>[[:word:]-.]+)/)?(?P<image>[[:word:]-.]+):(?P<tag>[[:word:][:punct:]]+)"#
-        ).unwrap();
+    fn parse_all(dockerfile: &str) -> Vec<(Image, Result<Annotation, CheckError>)> {
+        parse_references(dockerfile)
+            .into_iter()
+            .map(|(reference, annotation)| (reference.image, annotation))
+            .collect()
     }
 
-    impl<'t> Matches<'t> {
-        pub fn first(dockerfile: &'t str) -> Option<Matches<'t>> {
-            STATEMENT.captures(dockerfile).map(Self::from_captures)
-        }
+    #[test]
+    fn extracts_full_statement() {
+        let dockerfile = "# uptag --pattern \"<!>.<>.<>-ce.0\"\nFROM gitlab/gitlab-ce:12.3.2-ce.0";
+        assert_eq!(
+            parse_all(dockerfile),
+            vec![(
+                Image {
+                    name: ImageName::User {
+                        user: "gitlab".into(),
+                        image: "gitlab-ce".into()
+                    },
+                    tag: "12.3.2-ce.0".to_string(),
+                },
+                Ok(Annotation::new(
+                    VersionExtractor::parse("<!>.<>.<>-ce.0").unwrap()
+                ))
+            )]
+        );
+    }
 
-        pub fn iter(dockerfile: &'t str) -> impl Iterator<Item = Matches<'t>> {
-            STATEMENT.captures_iter(dockerfile).map(Self::from_captures)
-        }
+    #[test]
+    fn locates_references() {
+        let dockerfile = "FROM ubuntu:18.04\n\n# uptag --pattern \"<!>.<>\"\nfrom  alpine:3.12";
+        let locations = parse_references(dockerfile)
+            .into_iter()
+            .map(|(reference, _)| reference.location.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(locations, vec!["Dockerfile:1:6", "Dockerfile:4:7"]);
+    }
 
-        fn from_captures(captures: regex::Captures<'t>) -> Matches<'t> {
-            Matches {
-                all: captures.get(0).unwrap(),
-                user: captures.name("user"),
-                image: captures.name("image").unwrap(),
-                tag: captures.name("tag").unwrap(),
-                pattern: captures.name("pattern"),
-            }
-        }
+    #[test]
+    fn extracts_regex_statement() {
+        let dockerfile = "# uptag --regex \"(?P<breaking>\\d+)u(\\d+)\"\nFROM openjdk:8u292";
+        assert_eq!(
+            parse_all(dockerfile),
+            vec![(
+                "openjdk:8u292".parse().unwrap(),
+                Ok(Annotation::new(
+                    VersionExtractor::from_regex(r"(?P<breaking>\d+)u(\d+)").unwrap()
+                ))
+            )]
+        );
+    }
 
-        pub fn pattern(&self) -> &Option<regex::Match<'t>> {
-            &self.pattern
-        }
+    #[test]
+    fn extracts_options() {
+        let dockerfile =
+            "# uptag --pattern \"<!>.<>\" --min-age 7d --ignore \"18.10\" --search-limit 500\nFROM ubuntu:18.04";
+        assert_eq!(
+            parse_all(dockerfile),
+            vec![(
+                "ubuntu:18.04".parse().unwrap(),
+                Ok(Annotation {
+                    extractor: VersionExtractor::parse("<!>.<>").unwrap(),
+                    min_age: Some(MinAge::days(7)),
+                    ignore: vec!["18.10".to_string()],
+                    image: None,
+                    search_limit: Some(500),
+                    note: None,
+                })
+            )]
+        );
+    }
 
-        // TODO: Use image's parsing.
-        pub fn image(&self) -> Image {
-            Image {
-                name: ImageName::new(
-                    self.user.map(|m| m.as_str().to_string()),
-                    self.image.as_str().to_string(),
-                ),
-                tag: self.tag.as_str().to_string(),
-            }
-        }
+    #[test]
+    fn skips_images_and_reports_invalid_options() {
+        let dockerfile = r#"
+# uptag --skip
+FROM ubuntu:18.04
 
-        pub fn extractor(&self) -> Option<Result<VersionExtractor, pattern::Error>> {
-            self.pattern.map(|m| VersionExtractor::parse(m.as_str()))
-        }
+# uptag --pattern "<!>.<>" --frobnicate
+FROM alpine:3.18
+"#;
+        assert_eq!(
+            parse_all(dockerfile),
+            vec![(
+                "alpine:3.18".parse().unwrap(),
+                Err(CheckError::InvalidOption(
+                    annotation::Error::UnknownOption {
+                        option: "frobnicate".to_string(),
+                        line: 5
+                    }
+                ))
+            )]
+        );
     }
 
-    impl<'t> Tagged for Matches<'t> {
-        fn tag(&self) -> &str {
-            self.tag.as_str()
-        }
+    #[test]
+    fn validates_current_tags() {
+        let dockerfile = r#"
+# uptag --pattern "<!>.<>"
+FROM ubuntu:18.04
+
+# uptag --pattern "<!>.<>.<>"
+FROM node:18-slim
+"#;
+        let validated = parse_all(dockerfile)
+            .into_iter()
+            .map(|(image, annotation)| validate(&image, &annotation.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            validated,
+            vec![
+                Ok(()),
+                Err(CheckError::InvalidCurrentTag {
+                    tag: "18-slim".to_string(),
+                    pattern: "<!>.<>.<>".to_string()
+                })
+            ]
+        );
     }
 
-    #[cfg(test)]
-    mod test {
-        use super::*;
+    #[test]
+    fn applies_build_args() {
+        let dockerfile = r#"
+# uptag --pattern "<!>.<>.<>"
+ARG NODE_VERSION=18.17.0
+FROM node:${NODE_VERSION}-slim
+"#;
+        let compose = "args:\n  NODE_VERSION: 20.5.1\n";
+        let build_args = vec![BuildArg {
+            name: "NODE_VERSION".to_string(),
+            value: "20.5.1".to_string(),
+            location: Location::new(Path::new("docker-compose.yml"), compose, 22..28),
+        }];
 
-        #[derive(Debug)]
-        struct ExpectedMatches {
-            image_name: ImageName,
-            image_tag: &'static str,
-            extractor: Option<Result<VersionExtractor, pattern::Error>>,
-        }
+        let references = parse_with_args(
+            Path::new("Dockerfile"),
+            dockerfile,
+            &Presets::builtin(),
+            &build_args,
+        )
+        .map(|(reference, _)| reference.to_string())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            references,
+            vec!["node:20.5.1-slim (ARG NODE_VERSION at docker-compose.yml:2:17)"]
+        );
+    }
 
-        impl<'t> PartialEq<Matches<'t>> for ExpectedMatches {
-            fn eq(&self, other: &Matches) -> bool {
-                let other_image = other.image();
-                self.image_name == other_image.name
-                    && self.image_tag == other_image.tag
-                    && self.extractor == other.extractor()
-            }
-        }
+    #[test]
+    fn extracts_minimal_statement() {
+        let dockerfile = "FROM ubuntu:14.04";
+        assert_eq!(
+            parse_all(dockerfile),
+            vec![(
+                "ubuntu:14.04".parse().unwrap(),
+                Err(CheckError::UnspecifiedPattern)
+            )]
+        );
+    }
+
+    #[test]
+    fn matches_image_with_special_characters() {
+        let dockerfile = "# uptag --pattern \"<!>.<>.<>\"\nFROM weird.user/weird.image:3.10.5";
+        assert_eq!(
+            parse_all(dockerfile)
+                .into_iter()
+                .map(|(image, _)| image)
+                .collect::<Vec<_>>(),
+            vec![Image {
+                name: ImageName::User {
+                    user: "weird.user".into(),
+                    image: "weird.image".into()
+                },
+                tag: "3.10.5".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn defaults_empty_tag_to_latest() {
+        let dockerfile = "FROM ubuntu";
+        assert_eq!(
+            parse_all(dockerfile),
+            vec![(
+                "ubuntu:latest".parse().unwrap(),
+                Err(CheckError::UnspecifiedPattern)
+            )]
+        );
+    }
+
+    #[test]
+    fn does_not_match_digest() {
+        let dockerfile =
+            "FROM ubuntu@bcf9d02754f659706860d04fd261207db010db96e782e2eb5d5bbd7168388b89";
+        assert_eq!(parse_all(dockerfile), vec![]);
+    }
+
+    #[test]
+    fn handles_flags_stages_and_comments() {
+        let dockerfile = r#"
+# uptag --pattern "<!>"
+# Build with the platform of the host.
+from --platform=$BUILDPLATFORM node:18 as build
 
-        impl<'t> PartialEq<ExpectedMatches> for Matches<'t> {
-            fn eq(&self, other: &ExpectedMatches) -> bool {
-                other == self
+FROM build
+FROM scratch
+"#;
+        assert_eq!(
+            parse_all(dockerfile),
+            vec![(
+                "node:18".parse().unwrap(),
+                Ok(Annotation::new(VersionExtractor::parse("<!>").unwrap()))
+            )]
+        );
+    }
+
+    #[test]
+    fn resolves_annotated_args_in_from() {
+        let dockerfile = r#"
+# uptag --pattern "<!>.<>.<>"
+ARG NODE_VERSION=18.17.0
+FROM node:${NODE_VERSION}-slim
+"#;
+        let references = parse_references(dockerfile);
+        assert_eq!(references.len(), 1);
+
+        let (reference, annotation) = &references[0];
+        assert_eq!(reference.image, "node:18.17.0-slim".parse().unwrap());
+        let variable = reference.variable.as_ref().unwrap();
+        assert_eq!(variable.name, "NODE_VERSION");
+        assert_eq!(variable.suffix, "-slim");
+        assert_eq!(&dockerfile[reference.location.span.clone()], "18.17.0");
+        assert_eq!(
+            reference.to_string(),
+            "node:18.17.0-slim (ARG NODE_VERSION at Dockerfile:3:18)"
+        );
+
+        let extractor = &annotation.as_ref().unwrap().extractor;
+        assert!(extractor.matches("18.18.2-slim"));
+        assert!(!extractor.matches("18.18.2"));
+    }
+
+    #[test]
+    fn prefers_annotation_on_from_over_arg() {
+        let dockerfile = r#"
+# uptag --pattern "<!>.<>.<>"
+ARG NODE_VERSION=18.17.0
+# uptag --pattern "<!>.<>.<>-alpine"
+FROM node:${NODE_VERSION}-alpine
+"#;
+        assert_eq!(
+            parse_all(dockerfile),
+            vec![(
+                "node:18.17.0-alpine".parse().unwrap(),
+                Ok(Annotation::new(
+                    VersionExtractor::parse("<!>.<>.<>-alpine").unwrap()
+                ))
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_unresolved_variables() {
+        let dockerfile = r#"FROM ubuntu:22.04 AS base
+ARG PY=3.11.4
+FROM python:${PY}-slim
+# uptag --skip
+FROM node:${MISSING}
+"#;
+        let references = parse_references(dockerfile);
+        assert_eq!(references.len(), 2);
+
+        let (reference, annotation) = &references[1];
+        assert_eq!(
+            reference.image,
+            Image {
+                name: ImageName::new(None, "python".to_string()),
+                tag: "${PY}-slim".to_string(),
             }
-        }
+        );
+        assert_eq!(reference.location.to_string(), "Dockerfile:3:6");
+        assert_eq!(
+            annotation,
+            &Err(CheckError::UnresolvedVariable(variables::Error::Unset(
+                "PY".to_string()
+            )))
+        );
+    }
 
-        // This workaround is currenctly necessary,
-        // because Rust does not correctly recognize that we can compare Option<Matches> to Option<ExpectedMatches>.
-        // For details see https://stackoverflow.com/a/49903940/3287963
-        macro_rules! assert_eq_option {
-            ($actual:expr, $expected:expr) => {
-                match ($actual, $expected) {
-                    (Some(a), Some(b)) => assert_eq!(a, b),
-                    (None, None) => (),
-                    (a, b) => panic!(
-                        r#"assertion failed: `(left == right)`
-    left: `{:?}`,
-   right: `{:?}`"#,
-                        &a, &b
-                    ),
-                }
-            };
-        }
+    #[test]
+    fn checks_variables_naming_an_image() {
+        let dockerfile = r#"
+FROM alpine:3.18
+# uptag --pattern "<!>.<>" --image postgres
+ENV PG_VERSION=15.3
+"#;
+        let references = parse_references(dockerfile);
+        assert_eq!(references.len(), 2);
 
-        #[test]
-        fn extracts_full_statement() {
-            let dockerfile =
-                "# uptag --pattern \"<!>.<>.<>-ce.0\"\nFROM gitlab/gitlab-ce:12.3.2-ce.0";
-            assert_eq_option!(
-                Matches::first(dockerfile),
-                Some(ExpectedMatches {
-                    image_name: ImageName::User {
-                        user: "gitlab".into(),
-                        image: "gitlab-ce".into()
-                    },
-                    image_tag: "12.3.2-ce.0",
-                    extractor: Some(VersionExtractor::parse("<!>.<>.<>-ce.0")),
-                })
-            );
-        }
+        let (reference, annotation) = &references[1];
+        assert_eq!(reference.image, "postgres:15.3".parse().unwrap());
+        assert_eq!(reference.variable.as_ref().unwrap().keyword, "ENV");
+        assert!(annotation.is_ok());
+    }
 
-        #[test]
-        fn extracts_minimal_statement() {
-            let dockerfile = "FROM ubuntu:14.04";
-            assert_eq_option!(
-                Matches::first(dockerfile),
-                Some(ExpectedMatches {
-                    image_name: ImageName::Official {
-                        image: "ubuntu".into()
-                    },
-                    image_tag: "14.04",
-                    extractor: None,
-                })
-            )
-        }
+    #[test]
+    fn reports_invalid_annotations_naming_an_image() {
+        let dockerfile = r#"
+# uptag --pattern "<!" --image postgres
+ARG PG=15.3
+FROM alpine:3.18
+"#;
+        let references = parse_references(dockerfile);
+        assert_eq!(references.len(), 2);
 
-        #[test]
-        fn matches_image_with_special_characters() {
-            let dockerfile = "FROM weird.user/weird.image:3.10.5";
-            assert_eq_option!(
-                Matches::first(dockerfile),
-                Some(ExpectedMatches {
-                    image_name: ImageName::User {
-                        user: "weird.user".into(),
-                        image: "weird.image".into()
-                    },
-                    image_tag: "3.10.5",
-                    extractor: None,
-                })
-            )
-        }
+        let (reference, annotation) = &references[0];
+        assert_eq!(reference.image, "postgres:15.3".parse().unwrap());
+        assert!(matches!(annotation, Err(CheckError::InvalidPattern { .. })));
+    }
 
-        #[test]
-        fn does_not_match_empty_tag() {
-            let dockerfile = "FROM ubuntu";
-            assert_eq!(Matches::first(dockerfile), None)
-        }
+    #[test]
+    fn checks_variables_naming_an_image_once() {
+        let dockerfile = r#"
+# uptag --pattern "<!>.<>.<>" --image node
+ARG NODE=18.17.0
+FROM node:${NODE}
+"#;
+        let references = parse_references(dockerfile);
+        assert_eq!(references.len(), 1);
+        assert_eq!(
+            references[0].0.to_string(),
+            "node:18.17.0 (ARG NODE at Dockerfile:3:10)"
+        );
+    }
 
-        #[test]
-        fn does_not_match_digest() {
-            let dockerfile =
-                "FROM ubuntu@bcf9d02754f659706860d04fd261207db010db96e782e2eb5d5bbd7168388b89";
-            assert_eq!(Matches::first(dockerfile), None)
-        }
+    #[test]
+    fn extracts_images_of_copy_and_run_mounts() {
+        let dockerfile = r#"
+FROM golang:1.21 AS build
+# uptag --pattern "<!>.<>.<>"
+COPY --from=hashicorp/terraform:1.5.7 /bin/terraform /usr/bin/
+# uptag --pattern "<!>.<>"
+RUN --mount=type=bind,from=alpine:3.18,source=/etc,target=/mnt ls /mnt
+COPY --from=build /app /app
+"#;
+        assert_eq!(
+            parse_all(dockerfile),
+            vec![
+                (
+                    "golang:1.21".parse().unwrap(),
+                    Err(CheckError::UnspecifiedPattern)
+                ),
+                (
+                    "hashicorp/terraform:1.5.7".parse().unwrap(),
+                    Ok(Annotation::new(
+                        VersionExtractor::parse("<!>.<>.<>").unwrap()
+                    ))
+                ),
+                (
+                    "alpine:3.18".parse().unwrap(),
+                    Ok(Annotation::new(VersionExtractor::parse("<!>.<>").unwrap()))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_annotation_block() {
+        let dockerfile = r#"
+# uptag images:
+#   node: --pattern "<!>.<>.<>"
+#   alpine: --pattern "<!>.<>"
+
+FROM node:18.17.0 AS build
+# uptag --pattern "<!>.<>.<>"
+FROM alpine:3.18.2
+"#;
+        assert_eq!(
+            parse_all(dockerfile),
+            vec![
+                (
+                    "node:18.17.0".parse().unwrap(),
+                    Ok(Annotation::new(
+                        VersionExtractor::parse("<!>.<>.<>").unwrap()
+                    ))
+                ),
+                (
+                    "alpine:3.18.2".parse().unwrap(),
+                    Ok(Annotation::new(
+                        VersionExtractor::parse("<!>.<>.<>").unwrap()
+                    ))
+                ),
+            ]
+        );
     }
 }
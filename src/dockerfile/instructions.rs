@@ -0,0 +1,499 @@
+use std::ops::Range;
+
+use crate::image::{Image, ImageName};
+
+/// A range of bytes in the Dockerfile.
+pub type Span = Range<usize>;
+
+/// A whitespace-separated part of an instruction, such as `FROM` or `--platform=linux/amd64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word<'a> {
+    pub text: &'a str,
+    pub span: Span,
+}
+
+/// A comment line, without its leading `#`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment<'a> {
+    pub text: &'a str,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction<'a> {
+    /// The keyword as written, e.g., `FROM` or `from`.
+    pub keyword: Word<'a>,
+    pub arguments: Vec<Word<'a>>,
+    /// The comments between the previous instruction and this one.
+    pub comments: Vec<Comment<'a>>,
+    /// From the start of the keyword to the end of the last argument.
+    pub span: Span,
+}
+
+impl<'a> Instruction<'a> {
+    /// Whether this instruction has the `keyword`, ignoring case.
+    pub fn is(&self, keyword: &str) -> bool {
+        self.keyword.text.eq_ignore_ascii_case(keyword)
+    }
+}
+
+const DEFAULT_ESCAPE: char = '\\';
+
+/// Splits a Dockerfile into its instructions.
+///
+/// Follows the `escape` parser directive, joins line continuations, skips the bodies of heredocs
+/// and collects the comments preceding each instruction.
+pub fn tokenize(input: &str) -> Vec<Instruction<'_>> {
+    let mut lines = Lines::new(input).peekable();
+    let escape = parse_directives(&mut lines);
+
+    let mut instructions = Vec::new();
+    let mut comments = Vec::new();
+    while let Some((offset, line)) = lines.next() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            let start = offset + (line.len() - trimmed.len());
+            comments.push(Comment {
+                text: comment,
+                span: start..offset + line.len(),
+            });
+            continue;
+        }
+
+        let mut words = Vec::new();
+        let mut segment = (offset, line);
+        loop {
+            let (segment_offset, segment_line) = segment;
+            let (content, continues) = match segment_line.trim_end().strip_suffix(escape) {
+                Some(content) => (content, true),
+                None => (segment_line, false),
+            };
+            words.extend(split_words(segment_offset, content));
+
+            if !continues {
+                break;
+            }
+            // Empty lines and comments do not end a continued instruction.
+            match lines.find(|(_, line)| {
+                let trimmed = line.trim_start();
+                !trimmed.is_empty() && !trimmed.starts_with('#')
+            }) {
+                Some(next) => segment = next,
+                None => break,
+            }
+        }
+
+        for delimiter in words.iter().filter_map(|word| heredoc_delimiter(word.text)) {
+            lines.find(|(_, line)| line.trim() == delimiter);
+        }
+
+        let mut words = words.into_iter();
+        if let Some(keyword) = words.next() {
+            let arguments = words.collect::<Vec<_>>();
+            let end = arguments.last().unwrap_or(&keyword).span.end;
+            instructions.push(Instruction {
+                span: keyword.span.start..end,
+                keyword,
+                arguments,
+                comments: std::mem::take(&mut comments),
+            });
+        }
+    }
+
+    instructions
+}
+
+/// Reads the parser directives at the start of the Dockerfile and returns the escape character.
+fn parse_directives<'a>(lines: &mut std::iter::Peekable<Lines<'a>>) -> char {
+    let mut escape = DEFAULT_ESCAPE;
+    while let Some((_, line)) = lines.peek() {
+        let directive = line
+            .trim()
+            .strip_prefix('#')
+            .and_then(|rest| rest.split_once('='))
+            .filter(|(key, _)| key.trim().chars().all(|c| c.is_ascii_alphabetic()));
+        match directive {
+            Some((key, value)) => {
+                if key.trim().eq_ignore_ascii_case("escape") {
+                    escape = value.trim().chars().next().unwrap_or(DEFAULT_ESCAPE);
+                }
+                lines.next();
+            }
+            None => break,
+        }
+    }
+    escape
+}
+
+/// The delimiter of a heredoc started by `word`, such as `EOF` for `<<EOF`, `<<-EOF` or `<<"EOF"`.
+fn heredoc_delimiter(word: &str) -> Option<&str> {
+    let start = word.find("<<")?;
+    let delimiter = word[start + 2..]
+        .trim_start_matches('-')
+        .trim_matches(|c| c == '"' || c == '\'');
+    if !delimiter.is_empty() && delimiter.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        Some(delimiter)
+    } else {
+        None
+    }
+}
+
+fn split_words(offset: usize, content: &str) -> impl Iterator<Item = Word<'_>> {
+    let mut rest = content;
+    let mut position = offset;
+    std::iter::from_fn(move || {
+        let start = rest.find(|c: char| !c.is_whitespace())?;
+        let end = rest[start..]
+            .find(char::is_whitespace)
+            .map(|length| start + length)
+            .unwrap_or_else(|| rest.len());
+        let word = Word {
+            text: &rest[start..end],
+            span: position + start..position + end,
+        };
+        rest = &rest[end..];
+        position += end;
+        Some(word)
+    })
+}
+
+/// The lines of the input with their byte offsets, without line endings.
+struct Lines<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn new(input: &'a str) -> Self {
+        Lines { input, offset: 0 }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.input.len() {
+            return None;
+        }
+
+        let rest = &self.input[self.offset..];
+        let (line, length) = match rest.find('\n') {
+            Some(end) => (&rest[..end], end + 1),
+            None => (rest, rest.len()),
+        };
+        let start = self.offset;
+        self.offset += length;
+        Some((start, line.strip_suffix('\r').unwrap_or(line)))
+    }
+}
+
+/// A `FROM` instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FromInstruction<'a> {
+    /// Flags such as `--platform=$BUILDPLATFORM`.
+    pub flags: Vec<Word<'a>>,
+    pub image: Word<'a>,
+    /// The name given with `AS`.
+    pub stage: Option<Word<'a>>,
+    pub base: Base<'a>,
+    pub comments: Vec<Comment<'a>>,
+    pub span: Span,
+}
+
+/// What a `FROM` instruction builds upon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Base<'a> {
+    /// An image from DockerHub, which defaults to the `latest` tag.
+    Image(Image),
+    /// An earlier build stage.
+    Stage(&'a str),
+    /// The empty `scratch` image.
+    Scratch,
+    /// A reference containing variables, e.g., `node:${NODE_VERSION}`.
+    Variables,
+    /// An image that cannot be checked, e.g., because it is pinned by digest or hosted on
+    /// another registry.
+    Unsupported,
+}
+
+/// Finds the `FROM` instructions in a Dockerfile.
+pub fn from_instructions(input: &str) -> Vec<FromInstruction<'_>> {
+    let mut stages = Vec::new();
+    tokenize(input)
+        .into_iter()
+        .filter(|instruction| instruction.is("FROM"))
+        .filter_map(|instruction| {
+            let from = FromInstruction::parse(instruction, &stages)?;
+            if let Some(stage) = &from.stage {
+                stages.push(stage.text);
+            }
+            Some(from)
+        })
+        .collect()
+}
+
+impl<'a> FromInstruction<'a> {
+    /// Parses a `FROM` instruction, given the names of the stages declared before it.
+    pub fn parse(instruction: Instruction<'a>, stages: &[&str]) -> Option<Self> {
+        let mut arguments = instruction.arguments.into_iter().peekable();
+        let mut flags = Vec::new();
+        while let Some(flag) = arguments.next_if(|word| word.text.starts_with("--")) {
+            flags.push(flag);
+        }
+        let image = arguments.next()?;
+        let stage = match (arguments.next(), arguments.next()) {
+            (Some(keyword), Some(name)) if keyword.text.eq_ignore_ascii_case("AS") => Some(name),
+            _ => None,
+        };
+
+        let base = parse_reference(image.text, stages);
+
+        Some(FromInstruction {
+            flags,
+            image,
+            stage,
+            base,
+            comments: instruction.comments,
+            span: instruction.span,
+        })
+    }
+}
+
+/// Finds the images that a `COPY` or `RUN` instruction uses, i.e., the references in
+/// `COPY --from=<reference>` and `RUN --mount=type=bind,from=<reference>`.
+pub fn image_sources<'a>(instruction: &Instruction<'a>) -> Vec<Word<'a>> {
+    let flags = instruction
+        .arguments
+        .iter()
+        .take_while(|word| word.text.starts_with("--"));
+
+    if instruction.is("COPY") {
+        flags
+            .filter_map(|flag| flag_value(flag, "--from="))
+            .collect()
+    } else if instruction.is("RUN") {
+        flags
+            .filter_map(|flag| flag_value(flag, "--mount="))
+            .flat_map(|mount| {
+                let mut offset = mount.span.start;
+                mount
+                    .text
+                    .split(',')
+                    .map(|option| {
+                        let word = Word {
+                            text: option,
+                            span: offset..offset + option.len(),
+                        };
+                        offset += option.len() + 1;
+                        word
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter_map(|option| flag_value(&option, "from="))
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// The part of the `word` after the `prefix`, such as `golang:1.21` in `--from=golang:1.21`.
+fn flag_value<'a>(word: &Word<'a>, prefix: &str) -> Option<Word<'a>> {
+    let value = word.text.strip_prefix(prefix)?;
+    Some(Word {
+        text: value,
+        span: word.span.start + prefix.len()..word.span.end,
+    })
+}
+
+/// Parses an image reference, given the names of the stages declared before it.
+///
+/// Stages can also be referred to by their index, e.g., `COPY --from=0`.
+pub fn parse_reference<'a>(reference: &'a str, stages: &[&str]) -> Base<'a> {
+    let is_stage = stages
+        .iter()
+        .any(|stage| stage.eq_ignore_ascii_case(reference))
+        || (!reference.is_empty() && reference.chars().all(|c| c.is_ascii_digit()));
+    if is_stage {
+        Base::Stage(reference)
+    } else {
+        parse_base(reference)
+    }
+}
+
+/// Parses an image reference that does not refer to a stage.
+pub fn parse_base(reference: &str) -> Base<'_> {
+    if reference == "scratch" {
+        return Base::Scratch;
+    }
+    if reference.contains('$') {
+        return Base::Variables;
+    }
+    if reference.contains('@') {
+        return Base::Unsupported;
+    }
+
+    let (name, tag) = split_tag(reference);
+    match ImageName::parse(name) {
+        Some(name) if !tag.is_empty() => Base::Image(Image {
+            name,
+            tag: tag.to_string(),
+        }),
+        _ => Base::Unsupported,
+    }
+}
+
+/// The image written in a `reference` whose variables cannot be substituted, e.g., `python:${PY}-slim`, with the
+/// variables kept as they are written.
+pub fn unresolved_image(reference: &str) -> Image {
+    let (name, tag) = split_tag(reference);
+    Image {
+        name: ImageName::parse(name).unwrap_or_else(|| ImageName::new(None, name.to_string())),
+        tag: tag.to_string(),
+    }
+}
+
+/// Splits the `reference` into the image's name and tag, which defaults to `latest`.
+fn split_tag(reference: &str) -> (&str, &str) {
+    // A colon after the last slash separates the tag, while one before it belongs to a registry's port.
+    let name_end = reference.rfind('/').map_or(0, |slash| slash + 1);
+    match reference[name_end..].find(':') {
+        Some(colon) => {
+            let colon = name_end + colon;
+            (&reference[..colon], &reference[colon + 1..])
+        }
+        None => (reference, "latest"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn texts<'a>(words: &[Word<'a>]) -> Vec<&'a str> {
+        words.iter().map(|word| word.text).collect()
+    }
+
+    #[test]
+    fn tokenizes_instructions_with_spans() {
+        let input = "# comment\nFROM ubuntu:18.04\nRUN apt-get update";
+        let instructions = tokenize(input);
+
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].keyword.text, "FROM");
+        assert_eq!(texts(&instructions[0].arguments), vec!["ubuntu:18.04"]);
+        assert_eq!(
+            &input[instructions[0].arguments[0].span.clone()],
+            "ubuntu:18.04"
+        );
+        assert_eq!(&input[instructions[0].span.clone()], "FROM ubuntu:18.04");
+        assert_eq!(instructions[0].comments[0].text, " comment");
+        assert!(instructions[1].comments.is_empty());
+    }
+
+    #[test]
+    fn joins_line_continuations() {
+        let input = "FROM \\\n  # a comment inside the instruction\n  node:18 \\\n  AS build\n";
+        let instructions = tokenize(input);
+
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(
+            texts(&instructions[0].arguments),
+            vec!["node:18", "AS", "build"]
+        );
+        assert_eq!(&input[instructions[0].arguments[0].span.clone()], "node:18");
+    }
+
+    #[test]
+    fn follows_escape_directive() {
+        let input = "# escape=`\nFROM `\n  mcr.microsoft.com/windows:1809\nRUN dir C:\\";
+        let instructions = tokenize(input);
+
+        assert_eq!(instructions.len(), 2);
+        assert!(instructions[0].comments.is_empty());
+        assert_eq!(
+            texts(&instructions[0].arguments),
+            vec!["mcr.microsoft.com/windows:1809"]
+        );
+        assert_eq!(texts(&instructions[1].arguments), vec!["dir", "C:\\"]);
+    }
+
+    #[test]
+    fn skips_heredoc_bodies() {
+        let input = "RUN <<EOF\nFROM ubuntu:18.04\nEOF\nfrom alpine:3.12";
+        let instructions = tokenize(input);
+
+        assert_eq!(instructions.len(), 2);
+        assert!(instructions[1].is("FROM"));
+        assert_eq!(texts(&instructions[1].arguments), vec!["alpine:3.12"]);
+    }
+
+    #[test]
+    fn parses_from_instructions() {
+        let input = "FROM --platform=$BUILDPLATFORM node:18 AS build\nfrom build\nFROM scratch";
+        let froms = from_instructions(input);
+
+        assert_eq!(froms.len(), 3);
+        assert_eq!(texts(&froms[0].flags), vec!["--platform=$BUILDPLATFORM"]);
+        assert_eq!(froms[0].base, Base::Image("node:18".parse().unwrap()));
+        assert_eq!(
+            froms[0].stage.as_ref().map(|stage| stage.text),
+            Some("build")
+        );
+        assert_eq!(froms[1].base, Base::Stage("build"));
+        assert_eq!(froms[2].base, Base::Scratch);
+    }
+
+    #[test]
+    fn finds_image_sources() {
+        let input = "COPY --chown=app --from=hashicorp/terraform:1.5.7 /bin/terraform /usr/bin/\n\
+            RUN --mount=type=bind,from=golang:1.21,source=/go,target=/go go version\n\
+            COPY --from=build /app /app\n\
+            RUN --from=ignored:1.0 echo";
+        let instructions = tokenize(input);
+
+        let sources = image_sources(&instructions[0]);
+        assert_eq!(texts(&sources), vec!["hashicorp/terraform:1.5.7"]);
+        assert_eq!(&input[sources[0].span.clone()], "hashicorp/terraform:1.5.7");
+
+        let sources = image_sources(&instructions[1]);
+        assert_eq!(texts(&sources), vec!["golang:1.21"]);
+        assert_eq!(&input[sources[0].span.clone()], "golang:1.21");
+
+        assert_eq!(
+            parse_reference(image_sources(&instructions[2])[0].text, &["build"]),
+            Base::Stage("build")
+        );
+        assert_eq!(parse_reference("0", &[]), Base::Stage("0"));
+        assert!(image_sources(&instructions[3]).is_empty());
+    }
+
+    #[test]
+    fn parses_bases() {
+        assert_eq!(
+            parse_base("ubuntu"),
+            Base::Image("ubuntu:latest".parse().unwrap())
+        );
+        assert_eq!(
+            parse_base("weird.user/weird.image:3.10.5"),
+            Base::Image(Image {
+                name: ImageName::User {
+                    user: "weird.user".into(),
+                    image: "weird.image".into()
+                },
+                tag: "3.10.5".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_base(
+                "ubuntu@sha256:bcf9d02754f659706860d04fd261207db010db96e782e2eb5d5bbd7168388b89"
+            ),
+            Base::Unsupported
+        );
+        assert_eq!(parse_base("localhost:5000/app/ui:1.0"), Base::Unsupported);
+        assert_eq!(parse_base("node:${NODE_VERSION}"), Base::Variables);
+    }
+}
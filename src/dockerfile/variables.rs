@@ -0,0 +1,393 @@
+use thiserror::Error;
+
+use super::instructions::{Instruction, Span};
+
+/// A variable declared by an `ARG` or `ENV` instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Declaration<'a> {
+    pub name: &'a str,
+    /// The value without surrounding quotes, if one is given.
+    pub value: Option<Value<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Value<'a> {
+    pub text: &'a str,
+    pub span: Span,
+}
+
+/// Finds the variables declared by an `ARG` or `ENV` instruction.
+///
+/// Supports `ARG NAME`, `ARG NAME=value`, `ENV NAME=value` and the legacy `ENV NAME value`,
+/// each with optionally quoted values.
+pub fn declarations<'a>(input: &'a str, instruction: &Instruction<'a>) -> Vec<Declaration<'a>> {
+    let arguments = &instruction.arguments;
+    if instruction.is("ENV") && arguments.len() >= 2 && !arguments[0].text.contains('=') {
+        let start = arguments[1].span.start;
+        let end = arguments.last().unwrap().span.end; // There are at least two arguments.
+        return vec![Declaration {
+            name: arguments[0].text,
+            value: Some(unquote(&input[start..end], start)),
+        }];
+    }
+
+    arguments
+        .iter()
+        .map(|word| match word.text.split_once('=') {
+            Some((name, value)) => Declaration {
+                name,
+                value: Some(unquote(value, word.span.start + name.len() + 1)),
+            },
+            None => Declaration {
+                name: word.text,
+                value: None,
+            },
+        })
+        .collect()
+}
+
+fn unquote(text: &str, start: usize) -> Value<'_> {
+    let is_quoted = text.len() >= 2
+        && (text.starts_with('"') && text.ends_with('"')
+            || text.starts_with('\'') && text.ends_with('\''));
+    if is_quoted {
+        Value {
+            text: &text[1..text.len() - 1],
+            span: start + 1..start + text.len() - 1,
+        }
+    } else {
+        Value {
+            text,
+            span: start..start + text.len(),
+        }
+    }
+}
+
+/// A part of a substituted text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment<'a> {
+    pub text: String,
+    pub source: Source<'a>,
+}
+
+impl<'a> Segment<'a> {
+    /// The variable whose value is the text, if any.
+    pub fn variable(&self) -> Option<&'a str> {
+        match self.source {
+            Source::Variable(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Where the text of a segment comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source<'a> {
+    /// The text is written in the template at the span.
+    Literal(Span),
+    /// The text is the value of the variable.
+    Variable(&'a str),
+    /// The text is the default of the unset variable, written in the template at the span.
+    Default { variable: &'a str, span: Span },
+}
+
+/// Substitutes the variables in the `template`, e.g., `node:${NODE_VERSION}-slim`.
+///
+/// Supports `$NAME`, `${NAME}`, `${NAME:-default}`, `${NAME-default}`, `${NAME:+alternative}`, `${NAME+alternative}`,
+/// `${NAME:?error}` and `${NAME?error}`, while `$$` is a literal `$`. Fails if a variable without default is unset,
+/// according to `lookup`.
+pub fn substitute<'a, 'v>(
+    template: &'a str,
+    lookup: impl Fn(&str) -> Option<&'v str>,
+) -> Result<Vec<Segment<'a>>, Error> {
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut segments = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = template[offset..].find('$').map(|start| offset + start) {
+        if start > offset {
+            segments.push(literal(template, offset..start));
+        }
+        let invalid = || Error::InvalidExpression(template[start..].to_string());
+
+        let after_dollar = start + 1;
+        if template[after_dollar..].starts_with('$') {
+            segments.push(literal(template, after_dollar..after_dollar + 1));
+            offset = after_dollar + 1;
+            continue;
+        }
+        let (expression, end) = if template[after_dollar..].starts_with('{') {
+            let close = after_dollar + template[after_dollar..].find('}').ok_or_else(invalid)?;
+            (after_dollar + 1..close, close + 1)
+        } else {
+            let end = template[after_dollar..]
+                .find(|c: char| !is_name(c))
+                .map_or(template.len(), |end| after_dollar + end);
+            (after_dollar..end, end)
+        };
+
+        let name_end = template[expression.clone()]
+            .find(|c: char| !is_name(c))
+            .map_or(expression.end, |end| expression.start + end);
+        let name = &template[expression.start..name_end];
+        if name.is_empty() {
+            return Err(invalid());
+        }
+        let modifier = &template[name_end..expression.end];
+        // The word following the modifier's operator, e.g., the default.
+        let word = |operator: &str| name_end + operator.len()..expression.end;
+
+        let value = lookup(name);
+        let is_set_and_not_empty = value.is_some_and(|value| !value.is_empty());
+        let variable = |value: &str| Segment {
+            text: value.to_string(),
+            source: Source::Variable(name),
+        };
+        let default = |operator: &str| {
+            let span = word(operator);
+            Segment {
+                text: template[span.clone()].to_string(),
+                source: Source::Default {
+                    variable: name,
+                    span,
+                },
+            }
+        };
+        let required = |operator: &str| {
+            let message = &template[word(operator)];
+            Error::Required {
+                name: name.to_string(),
+                message: if message.is_empty() {
+                    "missing a value".to_string()
+                } else {
+                    message.to_string()
+                },
+            }
+        };
+        let segment = if modifier.starts_with(":-") {
+            match value {
+                Some(value) if is_set_and_not_empty => Some(variable(value)),
+                _ => Some(default(":-")),
+            }
+        } else if modifier.starts_with('-') {
+            Some(value.map_or_else(|| default("-"), variable))
+        } else if modifier.starts_with(":+") {
+            Some(literal(template, word(":+"))).filter(|_| is_set_and_not_empty)
+        } else if modifier.starts_with('+') {
+            Some(literal(template, word("+"))).filter(|_| value.is_some())
+        } else if modifier.starts_with(":?") {
+            match value {
+                Some(value) if is_set_and_not_empty => Some(variable(value)),
+                _ => return Err(required(":?")),
+            }
+        } else if modifier.starts_with('?') {
+            Some(variable(value.ok_or_else(|| required("?"))?))
+        } else if modifier.is_empty() {
+            Some(variable(
+                value.ok_or_else(|| Error::Unset(name.to_string()))?,
+            ))
+        } else {
+            return Err(invalid());
+        };
+        segments.extend(segment.filter(|segment| !segment.text.is_empty()));
+
+        offset = end;
+    }
+    if offset < template.len() {
+        segments.push(literal(template, offset..template.len()));
+    }
+
+    Ok(segments)
+}
+
+fn literal(template: &str, span: Span) -> Segment<'_> {
+    Segment {
+        text: template[span.clone()].to_string(),
+        source: Source::Literal(span),
+    }
+}
+
+/// Finds the only segment that holds part of the tag without being written out, i.e., a variable's value or default.
+///
+/// The tag spans the last `tag_length` bytes of the substituted text and has to follow a colon. Returns the segment
+/// together with the parts of the tag before and after it, or `None` if there is no such single segment or it holds
+/// more than the tag.
+pub fn tag_segment<'s, 'a>(
+    segments: &'s [Segment<'a>],
+    tag_length: usize,
+) -> Option<(String, &'s Segment<'a>, String)> {
+    let substituted = segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .collect::<String>();
+    let tag_start = substituted.len().checked_sub(tag_length)?;
+    if !substituted[..tag_start].ends_with(':') {
+        return None;
+    }
+
+    let mut prefix = String::new();
+    let mut found = None;
+    let mut suffix = String::new();
+    let mut offset = 0;
+    for segment in segments {
+        let end = offset + segment.text.len();
+        if end > tag_start {
+            let in_tag = &segment.text[tag_start.saturating_sub(offset)..];
+            match segment.source {
+                Source::Literal(_) if found.is_some() => suffix.push_str(in_tag),
+                Source::Literal(_) => prefix.push_str(in_tag),
+                _ if offset < tag_start || found.is_some() => return None,
+                _ => found = Some(segment),
+            }
+        }
+        offset = end;
+    }
+    Some((prefix, found?, suffix))
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("The variable `{0}` is not set")]
+    Unset(String),
+    #[error("The variable `{name}` is required but not set: {message}")]
+    Required { name: String, message: String },
+    #[error("The expression `{0}` is invalid")]
+    InvalidExpression(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::dockerfile::instructions::tokenize;
+
+    #[test]
+    fn finds_declarations() {
+        let input = "ARG NODE_VERSION=\"18.17.0\" DEBUG\nENV PATH /usr/local/bin";
+        let instructions = tokenize(input);
+
+        let args = declarations(input, &instructions[0]);
+        assert_eq!(args[0].name, "NODE_VERSION");
+        let value = args[0].value.as_ref().unwrap();
+        assert_eq!(value.text, "18.17.0");
+        assert_eq!(&input[value.span.clone()], "18.17.0");
+        assert_eq!(args[1].value, None);
+
+        let envs = declarations(input, &instructions[1]);
+        assert_eq!(envs[0].name, "PATH");
+        assert_eq!(envs[0].value.as_ref().unwrap().text, "/usr/local/bin");
+    }
+
+    #[test]
+    fn substitutes_variables() {
+        let lookup = |name: &str| match name {
+            "VERSION" => Some("18.17.0"),
+            "EMPTY" => Some(""),
+            _ => None,
+        };
+        let substituted = |template| {
+            substitute(template, lookup).map(|segments| {
+                segments
+                    .into_iter()
+                    .map(|segment| (segment.text, segment.source))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            substituted("node:${VERSION}-slim"),
+            Ok(vec![
+                ("node:".to_string(), Source::Literal(0..5)),
+                ("18.17.0".to_string(), Source::Variable("VERSION")),
+                ("-slim".to_string(), Source::Literal(15..20))
+            ])
+        );
+        assert_eq!(
+            substituted("node:$VERSION"),
+            Ok(vec![
+                ("node:".to_string(), Source::Literal(0..5)),
+                ("18.17.0".to_string(), Source::Variable("VERSION"))
+            ])
+        );
+        assert_eq!(
+            substituted("node:${EMPTY:-16}${MISSING:+-slim}"),
+            Ok(vec![
+                ("node:".to_string(), Source::Literal(0..5)),
+                (
+                    "16".to_string(),
+                    Source::Default {
+                        variable: "EMPTY",
+                        span: 14..16
+                    }
+                )
+            ])
+        );
+        assert_eq!(
+            substituted("node:${VERSION:?}${MISSING+-slim}"),
+            Ok(vec![
+                ("node:".to_string(), Source::Literal(0..5)),
+                ("18.17.0".to_string(), Source::Variable("VERSION"))
+            ])
+        );
+        assert_eq!(
+            substituted("node:${MISSING}"),
+            Err(Error::Unset("MISSING".to_string()))
+        );
+        assert_eq!(
+            substituted("node:${MISSING?Set the version}"),
+            Err(Error::Required {
+                name: "MISSING".to_string(),
+                message: "Set the version".to_string()
+            })
+        );
+        assert_eq!(
+            substituted("node:${EMPTY:?}"),
+            Err(Error::Required {
+                name: "EMPTY".to_string(),
+                message: "missing a value".to_string()
+            })
+        );
+        assert_eq!(
+            substituted("node:$${VERSION}-$$$VERSION"),
+            Ok(vec![
+                ("node:".to_string(), Source::Literal(0..5)),
+                ("$".to_string(), Source::Literal(6..7)),
+                ("{VERSION}-".to_string(), Source::Literal(7..17)),
+                ("$".to_string(), Source::Literal(18..19)),
+                ("18.17.0".to_string(), Source::Variable("VERSION"))
+            ])
+        );
+        assert_eq!(
+            substituted("node:${VERSION"),
+            Err(Error::InvalidExpression("${VERSION".to_string()))
+        );
+    }
+
+    #[test]
+    fn finds_tag_segments() {
+        let lookup = |name: &str| match name {
+            "VERSION" => Some("18.17.0"),
+            "IMAGE" => Some("node:18.17.0"),
+            _ => None,
+        };
+        let tag_segment = |template, tag: &str| {
+            let segments = substitute(template, lookup).unwrap();
+            tag_segment(&segments, tag.len())
+                .map(|(prefix, segment, suffix)| (prefix, segment.text.clone(), suffix))
+        };
+
+        assert_eq!(
+            tag_segment("node:v${VERSION}-slim", "v18.17.0-slim"),
+            Some(("v".to_string(), "18.17.0".to_string(), "-slim".to_string()))
+        );
+        assert_eq!(
+            tag_segment("postgres:${PG_VERSION:-15.3}", "15.3"),
+            Some((String::new(), "15.3".to_string(), String::new()))
+        );
+        assert_eq!(tag_segment("${IMAGE}", "18.17.0"), None);
+        assert_eq!(
+            tag_segment("node:${VERSION}${VERSION}", "18.17.018.17.0"),
+            None
+        );
+        assert_eq!(tag_segment("node:18.17.0", "18.17.0"), None);
+    }
+}
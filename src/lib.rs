@@ -1,24 +1,68 @@
+pub mod annotate;
+pub mod annotation;
+pub mod config;
 pub mod docker_compose;
 pub mod dockerfile;
 pub mod image;
+pub mod lint;
+pub mod location;
 pub mod pattern;
 pub mod report;
+pub mod rewrite;
+pub mod suggest;
 pub mod tag_fetcher;
 pub mod version;
 
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use image::Image;
 use tag_fetcher::TagFetcher;
 use version::extractor::VersionExtractor;
-use version::UpdateType;
+use version::{UpdateType, Version};
 
 pub fn find_update<T>(
     fetcher: &T,
     image: &Image,
     extractor: &VersionExtractor,
 ) -> Result<Update, FindUpdateError<T::FetchError>>
+where
+    T: TagFetcher,
+{
+    find_update_with(fetcher, image, extractor, &UpdateOptions::default())
+}
+
+/// Adjusts how `find_update_with` searches for updates.
+#[derive(Debug, Clone)]
+pub struct UpdateOptions {
+    /// Whether to collect all newer tags in `Update::newer`, instead of only the newest of each type.
+    pub all_versions: bool,
+    /// How long ago a tag must have been pushed to be considered an update.
+    pub min_age: Option<MinAge>,
+    /// The point in time that `min_age` is measured from.
+    pub now: DateTime<Utc>,
+    /// Tags that must not be suggested as updates.
+    pub ignore: Vec<Ignore>,
+}
+
+impl Default for UpdateOptions {
+    fn default() -> Self {
+        UpdateOptions {
+            all_versions: false,
+            min_age: None,
+            now: Utc::now(),
+            ignore: Vec::new(),
+        }
+    }
+}
+
+pub fn find_update_with<T>(
+    fetcher: &T,
+    image: &Image,
+    extractor: &VersionExtractor,
+    options: &UpdateOptions,
+) -> Result<Update, FindUpdateError<T::FetchError>>
 where
     T: TagFetcher,
 {
@@ -28,22 +72,24 @@ where
             .extract_from(&image.tag)
             .ok_or(FindUpdateError::CurrentTagPatternConflict {
                 current_tag: image.tag.to_string(),
-                pattern: extractor.pattern().to_string(),
+                pattern: extractor.to_string(),
             })?;
 
-    let mut breaking_update = None;
+    let mut update = Update::default();
+    let mut breaking_lines = Vec::new();
+    let mut encountered_update = false;
+    let mut encountered_current = false;
 
     let mut searched_amount = 0;
     for tag_result in fetcher.fetch(&image.name) {
         searched_amount += 1;
 
-        let tag_candidate = tag_result?;
+        let tag_info = tag_result?;
+        let tag_candidate = tag_info.name;
 
         if &tag_candidate == current_tag {
-            return Ok(Update {
-                compatible: None,
-                breaking: breaking_update,
-            });
+            encountered_current = true;
+            break;
         }
 
         if let Some(version_candidate) = extractor.extract_from(&tag_candidate) {
@@ -51,38 +97,249 @@ where
                 continue;
             }
 
-            match version_candidate
-                .update_type(&current_version, extractor.pattern().breaking_degree())
+            let update_type = version_candidate.update_type(&current_version, extractor);
+
+            if let Some(ignore) = options
+                .ignore
+                .iter()
+                .find(|ignore| ignore.applies_to(&tag_candidate, &version_candidate, extractor))
             {
-                UpdateType::Breaking => {
-                    breaking_update = breaking_update.or(Some(tag_candidate));
-                }
-                UpdateType::Compatible => {
-                    return Ok(Update {
-                        compatible: Some(tag_candidate),
-                        breaking: breaking_update,
-                    })
+                update.ignored.push(IgnoredTag {
+                    tag: tag_candidate,
+                    update_type,
+                    reason: ignore.reason.clone(),
+                });
+                continue;
+            }
+
+            // Tags without a known push date are assumed to be old enough.
+            let qualifies_on = options
+                .min_age
+                .zip(tag_info.last_updated)
+                .map(|(min_age, last_updated)| last_updated + min_age.duration())
+                .filter(|qualifies_on| qualifies_on > &options.now);
+            if let Some(qualifies_on) = qualifies_on {
+                update.pending.push(PendingTag {
+                    tag: tag_candidate,
+                    update_type,
+                    qualifies_on,
+                });
+                continue;
+            }
+
+            encountered_update = true;
+
+            if update_type == UpdateType::Breaking {
+                let line = version_candidate.breaking_line(extractor);
+                if !breaking_lines.iter().any(|(other, _)| other == &line) {
+                    breaking_lines.push((line, tag_candidate.clone()));
                 }
             }
+
+            if options.all_versions {
+                update
+                    .newer
+                    .get_mut(update_type)
+                    .push(tag_candidate.clone());
+            }
+
+            // The tags are ordered from newest to oldest, so we keep the first we encounter.
+            // We continue searching until we encounter the current tag, because a newer
+            // update of another type might be older than the first compatible update.
+            let newest = match update_type {
+                UpdateType::Breaking => &mut update.breaking,
+                UpdateType::Minor => &mut update.minor,
+                UpdateType::Compatible => &mut update.compatible,
+            };
+            if newest.is_none() {
+                *newest = Some(tag_candidate);
+            }
         }
     }
 
-    if breaking_update.is_some() {
-        Ok(Update {
-            compatible: None,
-            breaking: breaking_update,
-        })
+    if encountered_current || encountered_update {
+        update.newer.compatible.reverse();
+        update.newer.minor.reverse();
+        update.newer.breaking.reverse();
+        breaking_lines.sort();
+        update.breaking_ladder = breaking_lines.into_iter().map(|(_, tag)| tag).collect();
+        Ok(update)
     } else {
         Err(FindUpdateError::CurrentTagNotEncountered { searched_amount })
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
 pub struct Update {
     pub compatible: Option<Tag>,
+    pub minor: Option<Tag>,
     pub breaking: Option<Tag>,
+    /// All tags newer than the current one, if requested via `UpdateOptions::all_versions`.
+    pub newer: NewerTags,
+    /// The newest tag of each breaking release line, ordered from oldest to newest line.
+    ///
+    /// For `node:16`, this might be the newest `18.x`, `20.x` and `22.x` tags.
+    pub breaking_ladder: Vec<Tag>,
+    /// Newer tags that were skipped because they are younger than `UpdateOptions::min_age`, ordered from newest to oldest.
+    pub pending: Vec<PendingTag>,
+    /// Newer tags that were skipped because of `UpdateOptions::ignore`, ordered from newest to oldest.
+    pub ignored: Vec<IgnoredTag>,
+    /// The note given in the image's annotation.
+    pub note: Option<String>,
 }
 
+impl Update {
+    pub fn get(&self, update_type: UpdateType) -> &Option<Tag> {
+        match update_type {
+            UpdateType::Breaking => &self.breaking,
+            UpdateType::Minor => &self.minor,
+            UpdateType::Compatible => &self.compatible,
+        }
+    }
+
+    /// Whether no update of any type was found.
+    ///
+    /// Tags in `pending` and `ignored` are not updates. They are noted alongside the updates, or alongside the image
+    /// if it has no updates.
+    pub fn is_empty(&self) -> bool {
+        self.most_severe_type().is_none()
+    }
+
+    /// The most severe type of update that was found, if any.
+    pub fn most_severe_type(&self) -> Option<UpdateType> {
+        [
+            UpdateType::Breaking,
+            UpdateType::Minor,
+            UpdateType::Compatible,
+        ]
+        .iter()
+        .copied()
+        .find(|update_type| self.get(*update_type).is_some())
+    }
+
+    /// The newest tag among the updates up to the `level`, e.g., the minor or compatible update for `UpdateType::Minor`.
+    pub fn newest_up_to(&self, level: UpdateType) -> Option<&Tag> {
+        [
+            UpdateType::Breaking,
+            UpdateType::Minor,
+            UpdateType::Compatible,
+        ]
+        .iter()
+        .filter(|update_type| **update_type <= level)
+        .find_map(|update_type| self.get(*update_type).as_ref())
+    }
+}
+
+/// The tags of each type of update, ordered from oldest to newest.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct NewerTags {
+    pub compatible: Vec<Tag>,
+    pub minor: Vec<Tag>,
+    pub breaking: Vec<Tag>,
+}
+
+impl NewerTags {
+    pub fn get(&self, update_type: UpdateType) -> &Vec<Tag> {
+        match update_type {
+            UpdateType::Breaking => &self.breaking,
+            UpdateType::Minor => &self.minor,
+            UpdateType::Compatible => &self.compatible,
+        }
+    }
+
+    fn get_mut(&mut self, update_type: UpdateType) -> &mut Vec<Tag> {
+        match update_type {
+            UpdateType::Breaking => &mut self.breaking,
+            UpdateType::Minor => &mut self.minor,
+            UpdateType::Compatible => &mut self.compatible,
+        }
+    }
+}
+
+/// A newer tag that is not yet old enough to be considered an update.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct PendingTag {
+    pub tag: Tag,
+    pub update_type: UpdateType,
+    /// When the tag will have reached the minimum age.
+    pub qualifies_on: DateTime<Utc>,
+}
+
+/// A tag to skip when searching for updates.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Ignore {
+    pub tag: Tag,
+    /// Why the tag is ignored, e.g., because it was yanked.
+    pub reason: Option<String>,
+}
+
+impl Ignore {
+    pub fn new(tag: Tag) -> Self {
+        Ignore { tag, reason: None }
+    }
+
+    /// Whether the entry ignores the `tag` with the `version`.
+    ///
+    /// The entry is either the tag itself or its version, which is extracted by the `extractor` or written as numbers
+    /// separated by `.`, e.g., `3.11.2` for `3.11.2-slim`.
+    pub fn applies_to(&self, tag: &str, version: &Version, extractor: &VersionExtractor) -> bool {
+        let parts = self
+            .tag
+            .split('.')
+            .map(|part| part.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>();
+        self.tag == tag
+            || extractor.extract_from(&self.tag).as_ref() == Some(version)
+            || parts.is_ok_and(|parts| version.parts() == parts.as_slice())
+    }
+}
+
+/// A newer tag that was skipped because it is ignored.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct IgnoredTag {
+    pub tag: Tag,
+    pub update_type: UpdateType,
+    pub reason: Option<String>,
+}
+
+/// The minimum number of days since a tag was pushed, written as `7d` or `7`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinAge {
+    days: u32,
+}
+
+impl MinAge {
+    pub fn days(days: u32) -> Self {
+        MinAge { days }
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::days(self.days.into())
+    }
+}
+
+impl std::str::FromStr for MinAge {
+    type Err = InvalidMinAge;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_suffix('d')
+            .unwrap_or(s)
+            .parse()
+            .map(MinAge::days)
+            .map_err(|_| InvalidMinAge(s.to_string()))
+    }
+}
+
+impl std::fmt::Display for MinAge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d", self.days)
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("The minimum age `{0}` is invalid (expected a number of days, e.g., `7d`)")]
+pub struct InvalidMinAge(String);
+
 type Tag = String;
 
 #[derive(Debug, Error, PartialEq)]
@@ -125,8 +382,10 @@ pub fn display_error(error: &impl std::error::Error) -> String {
 mod test {
     use super::*;
 
+    use chrono::TimeZone;
+
     use crate::image::ImageName;
-    use crate::tag_fetcher::test::ArrayFetcher;
+    use crate::tag_fetcher::{test::ArrayFetcher, TagInfo};
 
     #[test]
     fn finds_compatible_update() {
@@ -152,7 +411,9 @@ mod test {
             actual,
             Update {
                 compatible: Some("14.05".to_string()),
+                minor: None,
                 breaking: None,
+                ..Update::default()
             },
         );
     }
@@ -181,7 +442,10 @@ mod test {
             actual,
             Update {
                 compatible: None,
+                minor: None,
                 breaking: Some("15.02".to_string()),
+                breaking_ladder: vec!["15.02".to_string()],
+                ..Update::default()
             },
         );
     }
@@ -211,11 +475,255 @@ mod test {
             actual,
             Update {
                 compatible: Some("14.05".to_string()),
+                minor: None,
                 breaking: Some("15.02".to_string()),
+                breaking_ladder: vec!["15.02".to_string()],
+                ..Update::default()
+            },
+        );
+    }
+
+    #[test]
+    fn finds_update_of_each_type() {
+        let image = Image {
+            name: ImageName::new(None, "node".to_string()),
+            tag: "14.4.0".to_string(),
+        };
+        let extractor = VersionExtractor::parse("<!>.<~>.<>").unwrap();
+
+        let fetcher = ArrayFetcher::with(
+            image.name.clone(),
+            vec![
+                "14.4.2".to_string(),
+                "15.0.0".to_string(),
+                "14.4.1".to_string(),
+                "14.5.0".to_string(),
+                "14.4.0".to_string(),
+                "14.3.0".to_string(),
+            ],
+        );
+
+        let result = find_update(&fetcher, &image, &extractor);
+        let actual = result.unwrap_or_else(|error| panic!("{}", error));
+        assert_eq!(
+            actual,
+            Update {
+                compatible: Some("14.4.2".to_string()),
+                minor: Some("14.5.0".to_string()),
+                breaking: Some("15.0.0".to_string()),
+                breaking_ladder: vec!["15.0.0".to_string()],
+                ..Update::default()
             },
         );
     }
 
+    #[test]
+    fn collects_all_newer_tags() {
+        let image = Image {
+            name: ImageName::new(None, "ubuntu".to_string()),
+            tag: "14.04".to_string(),
+        };
+        let extractor = VersionExtractor::parse("<!>.<>").unwrap();
+
+        let fetcher = ArrayFetcher::with(
+            image.name.clone(),
+            vec![
+                "15.01".to_string(),
+                "14.07".to_string(),
+                "14.06".to_string(),
+                "14.05".to_string(),
+                "14.04".to_string(),
+                "14.03".to_string(),
+            ],
+        );
+
+        let options = UpdateOptions {
+            all_versions: true,
+            ..UpdateOptions::default()
+        };
+        let result = find_update_with(&fetcher, &image, &extractor, &options);
+        let actual = result.unwrap_or_else(|error| panic!("{}", error));
+        assert_eq!(
+            actual.newer,
+            NewerTags {
+                compatible: vec![
+                    "14.05".to_string(),
+                    "14.06".to_string(),
+                    "14.07".to_string()
+                ],
+                minor: vec![],
+                breaking: vec!["15.01".to_string()],
+            }
+        );
+        assert_eq!(actual.compatible, Some("14.07".to_string()));
+    }
+
+    #[test]
+    fn finds_newest_tag_of_each_breaking_line() {
+        let image = Image {
+            name: ImageName::new(None, "node".to_string()),
+            tag: "16.1.0".to_string(),
+        };
+        let extractor = VersionExtractor::parse("<!>.<>.<>").unwrap();
+
+        let fetcher = ArrayFetcher::with(
+            image.name.clone(),
+            vec![
+                "20.2.0".to_string(),
+                "18.3.1".to_string(),
+                "20.1.0".to_string(),
+                "18.3.0".to_string(),
+                "16.2.0".to_string(),
+                "16.1.0".to_string(),
+            ],
+        );
+
+        let result = find_update(&fetcher, &image, &extractor);
+        let actual = result.unwrap_or_else(|error| panic!("{}", error));
+        assert_eq!(actual.breaking, Some("20.2.0".to_string()));
+        assert_eq!(
+            actual.breaking_ladder,
+            vec!["18.3.1".to_string(), "20.2.0".to_string()]
+        );
+    }
+
+    #[test]
+    fn skips_tags_younger_than_min_age() {
+        let image = Image {
+            name: ImageName::new(None, "ubuntu".to_string()),
+            tag: "14.04".to_string(),
+        };
+        let extractor = VersionExtractor::parse("<!>.<>").unwrap();
+
+        let now = Utc.with_ymd_and_hms(2022, 6, 10, 12, 0, 0).unwrap();
+        let tag = |name: &str, days_ago| TagInfo {
+            name: name.to_string(),
+            last_updated: Some(now - Duration::days(days_ago)),
+        };
+        let fetcher = ArrayFetcher::with_infos(
+            image.name.clone(),
+            vec![tag("14.06", 2), tag("14.05", 10), tag("14.04", 30)],
+        );
+
+        let options = UpdateOptions {
+            min_age: Some(MinAge::days(7)),
+            now,
+            ..UpdateOptions::default()
+        };
+        let result = find_update_with(&fetcher, &image, &extractor, &options);
+        let actual = result.unwrap_or_else(|error| panic!("{}", error));
+        assert_eq!(actual.compatible, Some("14.05".to_string()));
+        assert_eq!(
+            actual.pending,
+            vec![PendingTag {
+                tag: "14.06".to_string(),
+                update_type: UpdateType::Compatible,
+                qualifies_on: now + Duration::days(5),
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_ignored_tags() {
+        let image = Image {
+            name: ImageName::new(None, "python".to_string()),
+            tag: "3.11.1".to_string(),
+        };
+        let extractor = VersionExtractor::parse("<!>.<~>.<>").unwrap();
+
+        let fetcher = ArrayFetcher::with(
+            image.name.clone(),
+            vec![
+                "3.11.2".to_string(),
+                "3.11.1".to_string(),
+                "3.11.0".to_string(),
+            ],
+        );
+
+        let options = UpdateOptions {
+            ignore: vec![Ignore {
+                tag: "3.11.2".to_string(),
+                reason: Some("Yanked upstream".to_string()),
+            }],
+            ..UpdateOptions::default()
+        };
+        let result = find_update_with(&fetcher, &image, &extractor, &options);
+        let actual = result.unwrap_or_else(|error| panic!("{}", error));
+        assert_eq!(actual.compatible, None);
+        assert_eq!(
+            actual.ignored,
+            vec![IgnoredTag {
+                tag: "3.11.2".to_string(),
+                update_type: UpdateType::Compatible,
+                reason: Some("Yanked upstream".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_ignored_versions_of_suffixed_tags() {
+        let image = Image {
+            name: ImageName::new(None, "python".to_string()),
+            tag: "3.11.1-slim".to_string(),
+        };
+        let extractor = VersionExtractor::parse("<!>.<>.<>-slim").unwrap();
+
+        let fetcher = ArrayFetcher::with(
+            image.name.clone(),
+            vec![
+                "3.11.3-slim".to_string(),
+                "3.11.2-slim".to_string(),
+                "3.11.1-slim".to_string(),
+            ],
+        );
+
+        let options = UpdateOptions {
+            ignore: vec![
+                Ignore::new("3.11.2".to_string()),
+                Ignore::new("3.11.3-slim".to_string()),
+            ],
+            ..UpdateOptions::default()
+        };
+        let result = find_update_with(&fetcher, &image, &extractor, &options);
+        let actual = result.unwrap_or_else(|error| panic!("{}", error));
+        assert_eq!(actual.compatible, None);
+        assert_eq!(
+            actual
+                .ignored
+                .into_iter()
+                .map(|ignored| ignored.tag)
+                .collect::<Vec<_>>(),
+            vec!["3.11.3-slim".to_string(), "3.11.2-slim".to_string()]
+        );
+    }
+
+    #[test]
+    fn parses_min_age() {
+        assert_eq!("7d".parse(), Ok(MinAge::days(7)));
+        assert_eq!("7".parse(), Ok(MinAge::days(7)));
+        assert_eq!("7w".parse::<MinAge>(), Err(InvalidMinAge("7w".to_string())));
+    }
+
+    #[test]
+    fn finds_newest_tag_up_to_level() {
+        let update = Update {
+            compatible: Some("14.4.1".to_string()),
+            minor: Some("14.5.0".to_string()),
+            ..Update::default()
+        };
+
+        assert_eq!(
+            update.newest_up_to("compatible".parse().unwrap()),
+            Some(&"14.4.1".to_string())
+        );
+        assert_eq!(
+            update.newest_up_to(UpdateType::Breaking),
+            Some(&"14.5.0".to_string())
+        );
+        assert_eq!(Update::default().newest_up_to(UpdateType::Breaking), None);
+        assert!("major".parse::<UpdateType>().is_err());
+    }
+
     #[test]
     fn ignores_lesser_version() {
         let image = Image {
@@ -239,7 +747,9 @@ mod test {
             actual,
             Update {
                 compatible: None,
+                minor: None,
                 breaking: None,
+                ..Update::default()
             },
         );
     }
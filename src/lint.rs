@@ -0,0 +1,193 @@
+use std::fmt;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::annotation::Annotation;
+use crate::display_error;
+use crate::dockerfile::{self, CheckError, ImageReference};
+use crate::image::Image;
+use crate::location::Location;
+
+/// A problem with an image that is found without accessing the network.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub location: Location,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, display_error(&self.problem))
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum Problem {
+    #[error("The image `{0}` is not annotated with a pattern")]
+    MissingAnnotation(Image),
+    #[error("The image `{0}` uses a floating tag, which may change at any time (pin a specific version instead)")]
+    FloatingTag(Image),
+    #[error("The tag of the image `{image}` does not match the pattern `{pattern}`")]
+    PatternMismatch { image: Image, pattern: String },
+    #[error("The annotation of the image `{image}` is invalid")]
+    InvalidAnnotation {
+        image: Image,
+        #[source]
+        source: CheckError,
+    },
+    #[error("The image `{image}` cannot be resolved")]
+    UnresolvedImage {
+        image: Image,
+        #[source]
+        source: CheckError,
+    },
+}
+
+/// Finds the problems of the `references`, such as missing annotations and floating tags.
+pub fn lint(
+    references: impl IntoIterator<Item = (ImageReference, Result<Annotation, CheckError>)>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (reference, annotation) in references {
+        let ImageReference {
+            image, location, ..
+        } = reference;
+        let mut problems = Vec::new();
+        let is_resolved = !matches!(annotation, Err(CheckError::UnresolvedVariable(_)));
+        if is_resolved && is_floating(&image.tag) {
+            problems.push(Problem::FloatingTag(image.clone()));
+        }
+        match annotation {
+            Ok(annotation) => {
+                if let Err(CheckError::InvalidCurrentTag { pattern, .. }) =
+                    dockerfile::validate(&image, &annotation)
+                {
+                    problems.push(Problem::PatternMismatch { image, pattern });
+                }
+            }
+            Err(CheckError::UnspecifiedPattern) => problems.push(Problem::MissingAnnotation(image)),
+            Err(error @ CheckError::UnresolvedVariable(_)) => {
+                problems.push(Problem::UnresolvedImage {
+                    image,
+                    source: error,
+                })
+            }
+            Err(error) => problems.push(Problem::InvalidAnnotation {
+                image,
+                source: error,
+            }),
+        }
+
+        diagnostics.extend(problems.into_iter().map(|problem| Diagnostic {
+            location: location.clone(),
+            problem,
+        }));
+    }
+    diagnostics
+}
+
+lazy_static! {
+    /// A single short number as version, optionally with a variant such as `-alpine`.
+    static ref BARE_MAJOR: Regex = Regex::new(r"^v?\d{1,3}(-[a-zA-Z].*)?$").unwrap();
+}
+
+/// Whether the `tag` refers to different images over time, such as `latest`, `lts` or a bare major version like `18`.
+pub fn is_floating(tag: &str) -> bool {
+    tag == "latest" || !tag.contains(|c: char| c.is_ascii_digit()) || BARE_MAJOR.is_match(tag)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::Path;
+
+    use crate::docker_compose::{self, BuildContext};
+    use crate::pattern::Presets;
+
+    fn lint_dockerfile(input: &str) -> Vec<String> {
+        lint(dockerfile::parse(
+            Path::new("Dockerfile"),
+            input,
+            &Presets::builtin(),
+        ))
+        .iter()
+        .map(Diagnostic::to_string)
+        .collect()
+    }
+
+    #[test]
+    fn finds_problems() {
+        let input = r#"FROM ubuntu
+# uptag --pattern "<!>.<>"
+FROM node:18-alpine
+# uptag --pattern "<!>.<>.<>"
+FROM python:3.11
+# uptag --pattern "<!>.<>" --min-age 7
+FROM debian:12.1
+# uptag --pattern "<!>.<>"
+FROM alpine:3.18
+ARG PY=3.11.4
+FROM python:${PY}-slim
+"#;
+
+        assert_eq!(
+            lint_dockerfile(input),
+            vec![
+                "Dockerfile:1:6: The image `ubuntu:latest` uses a floating tag, which may change at any time (pin a specific version instead)",
+                "Dockerfile:1:6: The image `ubuntu:latest` is not annotated with a pattern",
+                "Dockerfile:3:6: The image `node:18-alpine` uses a floating tag, which may change at any time (pin a specific version instead)",
+                "Dockerfile:3:6: The tag of the image `node:18-alpine` does not match the pattern `<!>.<>`",
+                "Dockerfile:5:6: The tag of the image `python:3.11` does not match the pattern `<!>.<>.<>`",
+                "Dockerfile:11:6: The image `python:${PY}-slim` cannot be resolved: Failed to substitute the variables, which have to be set by an `ARG` before the first `FROM`: The variable `PY` is not set",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_problems_in_compose_file() {
+        let input = r#"services:
+  web:
+    image: nginx
+  app:
+    build:
+      additional_contexts:
+        base: docker-image://alpine:3.18
+  db:
+    # uptag --pattern "<!>.<>"
+    image: postgres:15.3
+"#;
+        let references =
+            docker_compose::parse(Path::new("docker-compose.yml"), input, &Presets::builtin())
+                .unwrap()
+                .into_iter()
+                .filter_map(|(_, build_context)| match build_context {
+                    BuildContext::Image(reference, annotation) => Some((reference, annotation)),
+                    BuildContext::Folder(..) => None,
+                });
+
+        assert_eq!(
+            lint(references)
+                .iter()
+                .map(Diagnostic::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "docker-compose.yml:3:12: The image `nginx:latest` uses a floating tag, which may change at any time (pin a specific version instead)",
+                "docker-compose.yml:3:12: The image `nginx:latest` is not annotated with a pattern",
+                "docker-compose.yml:7:30: The image `alpine:3.18` is not annotated with a pattern",
+            ]
+        );
+    }
+
+    #[test]
+    fn recognizes_floating_tags() {
+        for tag in &["latest", "lts", "alpine", "18", "v18", "18-slim"] {
+            assert!(is_floating(tag), "{} should be floating", tag);
+        }
+        for tag in &["18.04", "3.18-alpine", "8u292-b10", "20231010"] {
+            assert!(!is_floating(tag), "{} should not be floating", tag);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Where something was found in a file, e.g., `Dockerfile:12:6`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub file: PathBuf,
+    /// The line of the start of the span, starting at 1.
+    pub line: usize,
+    /// The column of the start of the span in characters, starting at 1.
+    pub column: usize,
+    /// The range of bytes in the file.
+    pub span: Range<usize>,
+}
+
+impl Location {
+    /// Locates the `span` in the `input`, which is the content of the `file`.
+    pub fn new(file: &Path, input: &str, span: Range<usize>) -> Self {
+        let before = &input[..span.start];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Location {
+            file: file.to_path_buf(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            span,
+        }
+    }
+
+    /// Locates the character at the `line` and `column`, both starting at 1, spanning `length` bytes.
+    ///
+    /// Returns `None` if the `input` has no such character.
+    pub fn at(file: &Path, input: &str, line: usize, column: usize, length: usize) -> Option<Self> {
+        let line_start = input
+            .split_inclusive('\n')
+            .take(line.checked_sub(1)?)
+            .map(str::len)
+            .sum::<usize>();
+        let start = line_start
+            + input[line_start..]
+                .char_indices()
+                .nth(column.checked_sub(1)?)?
+                .0;
+        Some(Location::new(file, input, start..start + length))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.display(), self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn locates_spans() {
+        let input = "# comment\nFROM ubuntu:18.04\n  image: ÿ:1.0";
+        let file = Path::new("Dockerfile");
+
+        let location = Location::new(file, input, 15..27);
+        assert_eq!((location.line, location.column), (2, 6));
+        assert_eq!(location.to_string(), "Dockerfile:2:6");
+
+        assert_eq!(Location::at(file, input, 2, 6, 12), Some(location));
+        let multibyte = Location::at(file, input, 3, 12, 3).unwrap();
+        assert_eq!(&input[multibyte.span], "1.0");
+        assert_eq!(Location::at(file, input, 4, 1, 0), None);
+    }
+}
@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::{self, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use indicatif::{ProgressBar, ProgressStyle};
 use itertools::Itertools;
 use lazy_static::lazy_static;
@@ -9,16 +9,22 @@ use structopt::StructOpt;
 use thiserror::Error;
 
 use docker_compose::BuildContext;
-use uptag::docker_compose;
+use uptag::annotate::Insertion;
+use uptag::annotation::Annotation;
+use uptag::config::Config;
+use uptag::docker_compose::{self, Environment};
 use uptag::dockerfile;
-use uptag::dockerfile::CheckError;
-use uptag::image::ImageName;
+use uptag::dockerfile::{BuildArg, CheckError, ImageReference};
+use uptag::image::{Image, ImageName};
 use uptag::report::{
     docker_compose::DockerComposeReport, dockerfile::DockerfileReport, UpdateLevel,
 };
+use uptag::rewrite::{self, Rewrite};
+use uptag::suggest;
 use uptag::tag_fetcher::{DockerHubTagFetcher, TagFetcher};
 use uptag::version::extractor::VersionExtractor;
-use uptag::FindUpdateError;
+use uptag::version::UpdateType;
+use uptag::{display_error, FindUpdateError, MinAge, Update, UpdateOptions};
 
 /// Check Docker image tags for updates.
 #[derive(Debug, StructOpt)]
@@ -27,15 +33,47 @@ PATTERN SYNTAX:
 Use `<>` to match a number. Everything else will be matched literally.
 - `<>.<>.<>` will match `2.13.3` but not `2.13.3a`.
 - `debian-<>-beta` will match `debian-10-beta` but not `debian-10`.
+- Literals may contain letters, digits, `_`, `.`, `-`, `+` and `~`, so `v<>.<>+build<>` will match `v1.2+build5`. To start the first alternative of an alternation with `~`, write `\\~`, since `(~` marks the alternation as minor.
+- `(<literal>|<literal>)` will match either literal, so `<>-(slim|slim-bookworm)` will match `18-slim` and `18-slim-bookworm`. Later alternatives are considered newer.
 
 Specify which numbers indicate breaking changes using `<!>`. Uptag will report breaking changes separately from compatible changes.
 - Given pattern `<!>.<>.<>` and the current tag `1.4.12`:
   - compatible updates: `1.6.12` and `1.4.13`
-  - breaking updates: `2.4.12` and `3.5.13`")]
+  - breaking updates: `2.4.12` and `3.5.13`
+- Any number can be marked as breaking. Given pattern `<>.<>-alpine<!>.<>` and the current tag `1.2-alpine3.18`:
+  - compatible updates: `2.0-alpine3.19`
+  - breaking updates: `1.2-alpine4.0`
+- Use `<~>` for numbers that indicate minor updates, which are reported separately from both breaking and compatible updates. Given pattern `<!>.<~>.<>` and the current tag `1.4.12`:
+  - compatible updates: `1.4.13`
+  - minor updates: `1.6.12`
+  - breaking updates: `2.4.12`
+- Changing between alternatives is compatible, unless marked as breaking with `(!<literal>|<literal>)` or as minor with `(~<literal>|<literal>)`.
+
+Instead of a pattern, you can give the name of a preset:
+- `semver` for `<!>.<>.<>`
+- `semver-alpine` for `<!>.<>.<>-alpine`
+- `calver` for `<>.<>.<>`, e.g., `2023.10.01`, where no update is breaking
+- `major.minor` for `<!>.<>`
+
+REGEX SYNTAX:
+For tags that patterns cannot express, use `--regex` instead of `--pattern`. The regex must match the entire tag. Each capture group must match a number. Groups named starting with `breaking` or `minor` indicate breaking or minor updates, while unnamed groups and groups named starting with `compatible` indicate compatible updates.
+- `(?P<breaking>\\d+)u(\\d+)-b(\\d+)` will match `8u292-b10`.
+
+Additional presets can be defined in a configuration file passed via `--config`:
+```
+presets:
+  debian: \"<!>.<>-bookworm\"
+```")]
 enum Opts {
     Fetch(Box<FetchOpts>),
     Check(CheckOpts),
     CheckCompose(CheckComposeOpts),
+    SuggestPattern(SuggestPatternOpts),
+    Update(UpdateOpts),
+    UpdateCompose(UpdateComposeOpts),
+    Lint(LintOpts),
+    Validate(ValidateOpts),
+    Annotate(AnnotateOpts),
 }
 
 /// Lists the latest tags for an image from DockerHub.
@@ -43,9 +81,12 @@ enum Opts {
 struct FetchOpts {
     /// The image name for which tags should be fetched.
     image: ImageName,
-    /// A pattern to filter the tags with. Only matching tags will be output.
+    /// A pattern or preset name to filter the tags with. Only matching tags will be output.
     #[structopt(short, long)]
-    pattern: Option<VersionExtractor>,
+    pattern: Option<String>,
+    /// A regex to filter the tags with, as an alternative to --pattern.
+    #[structopt(short, long, conflicts_with = "pattern")]
+    regex: Option<String>,
     /// The maximum number of tags to output.
     #[structopt(short, long, default_value = "25")]
     amount: usize,
@@ -56,12 +97,15 @@ struct FetchOpts {
     /// Example: `uptag fetch --amount 50 --search-limit 500 --pattern '<!>.<>' ubuntu` will stop after 50 matching tags or after looking through the latest 500 tags, whichever happens first.
     #[structopt(short, long, default_value = "100")]
     search_limit: usize,
+    /// A configuration file defining additional pattern presets.
+    #[structopt(short, long, parse(from_os_str))]
+    config: Option<PathBuf>,
 }
 
 /// Reports on update status for all images in a Dockerfile.
 #[derive(Debug, StructOpt)]
 #[structopt(after_help = r#"SPECIFYING PATTERNS:
-Each `FROM` definition needs to be annotated with a pattern and declare a specific tag that matches that pattern. The pattern must be given as a comment in the line before each `FROM <image>:<tag>` definition in the following format:
+Each `FROM` definition needs to be annotated with a pattern and declare a specific tag that matches that pattern. Images without a tag are checked as `latest`, while build stages, `scratch` and images pinned by digest are skipped. The pattern must be given as a comment above each `FROM <image>:<tag>` definition, with only other comments or empty lines in between, in the following format:
 # uptag --pattern "<pattern>"
 
 Example `Dockerfile`:
@@ -71,7 +115,30 @@ FROM ubuntu:18.04
 
 # uptag --pattern "<!>.<>.<>-slim"
 FROM node:14.5.0-slim
-```"#)]
+```
+
+For tags that patterns cannot express, a regex can be given instead in the format:
+# uptag --regex "<regex>"
+See `uptag help` for details.
+
+Images used by `COPY --from=<image>` and `RUN --mount=from=<image>` are annotated in the same way as `FROM` definitions.
+
+An annotation can also override the minimum age of tags with `--min-age` and the search limit with `--search-limit`, exclude tags with `--ignore`, and add a note to the report with `--note`:
+# uptag --pattern "<pattern>" --min-age 7d --ignore "<tag>" --search-limit 500 --note "<text>"
+Images annotated with `# uptag --skip` are not checked at all.
+
+If the tag is held in a variable, annotate the `ARG` instead. Its pattern only covers the variable's value:
+```
+# uptag --pattern "<!>.<>.<>"
+ARG NODE_VERSION=18.17.0
+FROM node:${NODE_VERSION}-slim
+```
+An `ARG` or `ENV` that is not used in a `FROM` can name its image with `--image`:
+# uptag --pattern "<!>.<>" --image postgres
+
+Images that are not annotated themselves can be annotated by name in a block of comments anywhere in the file:
+# uptag images:
+#   node: --pattern "<!>.<>.<>""#)]
 struct CheckOpts {
     /// The Dockerfile to check.
     #[structopt(parse(from_os_str))]
@@ -79,6 +146,15 @@ struct CheckOpts {
     /// Limits how many tags will be fetched from DockerHub before stopping the search.
     #[structopt(short, long, default_value = "100")]
     search_limit: usize,
+    /// A configuration file defining additional pattern presets and ignored tags.
+    #[structopt(short, long, parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Lists every newer tag of each update, instead of only the newest one.
+    #[structopt(long)]
+    all_versions: bool,
+    /// Only considers tags pushed at least this many days ago, e.g., `7d`. Annotations can override this with `--min-age`.
+    #[structopt(long)]
+    min_age: Option<MinAge>,
 }
 
 /// Reports on update status for all services in a docker-compose file.
@@ -86,11 +162,13 @@ struct CheckOpts {
 #[structopt(after_help = r#"SPECIFYING PATTERNS:
 Each service must associate a pattern with its images. There are two supported declarations.
 
-A service can specify an `image` field, pointing to an image on DockerHub. Such an image needs to be annotated with a pattern and declare a specific tag that matches that pattern. The pattern must be given as a comment in the line before the `image` field in the following format:
+A service can specify an `image` field, pointing to an image on DockerHub. Such an image needs to be annotated with a pattern and declare a specific tag that matches that pattern. The pattern must be given as a comment above the `image` field, with only other comments or empty lines in between, or as a trailing comment on the same line, in the following format:
 # uptag --pattern "<pattern>"
 
 Alternatively, a service can point to a folder containing a Dockerfile via its `build` field. That Dockerfile needs to specify patterns as documented in `uptag check --help`.
 
+The `build` field may also be a mapping with `context`, `dockerfile`, `args` and `additional_contexts`. The `args` override the defaults of the Dockerfile's `ARG`s, and images used as `docker-image://` additional contexts are annotated like `image` fields.
+
 Example `docker-compose.yml`:
 ```
 version: "3.6"
@@ -102,7 +180,11 @@ services:
 
   alpine:
     build: ./alpine
-```"#)]
+```
+
+Images that are not annotated themselves can be annotated by name in a block of comments anywhere in the file:
+# uptag images:
+#   node: --pattern "<!>.<>.<>""#)]
 struct CheckComposeOpts {
     /// The docker-compose file to check.
     #[structopt(parse(from_os_str))]
@@ -110,6 +192,148 @@ struct CheckComposeOpts {
     /// Limits how many tags will be fetched from DockerHub before stopping the search.
     #[structopt(short, long, default_value = "100")]
     search_limit: usize,
+    /// A configuration file defining additional pattern presets and ignored tags.
+    #[structopt(short, long, parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Lists every newer tag of each update, instead of only the newest one.
+    #[structopt(long)]
+    all_versions: bool,
+    /// Only considers tags pushed at least this many days ago, e.g., `7d`. Annotations can override this with `--min-age`.
+    #[structopt(long)]
+    min_age: Option<MinAge>,
+}
+
+/// Rewrites the tags in a Dockerfile to their newest updates.
+///
+/// Only the tags are replaced, so formatting, comments and annotations stay as they are.
+/// Tags held in an annotated `ARG` or `ENV` are updated where the variable is declared.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    after_help = r#"Images are annotated as documented in `uptag check --help`.
+
+The file is only written if every new tag still matches its image's pattern, and it is replaced atomically. Images that fail to be checked are reported and left as they are, in which case the exit code is 10."#
+)]
+struct UpdateOpts {
+    /// The Dockerfile to update.
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+    /// The most severe type of update to apply: `compatible`, `minor` or `breaking`.
+    #[structopt(short, long, default_value = "compatible", possible_values = &["compatible", "minor", "breaking"])]
+    level: UpdateType,
+    /// Limits how many tags will be fetched from DockerHub before stopping the search.
+    #[structopt(short, long, default_value = "100")]
+    search_limit: usize,
+    /// A configuration file defining additional pattern presets and ignored tags.
+    #[structopt(short, long, parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Only considers tags pushed at least this many days ago, e.g., `7d`. Annotations can override this with `--min-age`.
+    #[structopt(long)]
+    min_age: Option<MinAge>,
+    /// Prints the changes as a unified diff instead of writing them. Exits with 1 if there are changes and 0 otherwise.
+    #[structopt(long)]
+    diff: bool,
+}
+
+/// Rewrites the tags in a docker-compose file and the Dockerfiles of its services to their newest updates.
+///
+/// Only the tags are replaced, so formatting, comments and annotations stay as they are.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    after_help = r#"Images are annotated as documented in `uptag check-compose --help`. The tags of `image` fields are rewritten in the docker-compose file, while services with a `build` folder have the tags in their Dockerfile rewritten.
+
+Files are only written if every new tag still matches its image's pattern, and each is replaced atomically. Images that fail to be checked are reported and left as they are, in which case the exit code is 10."#
+)]
+struct UpdateComposeOpts {
+    /// The docker-compose file to update.
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+    /// The most severe type of update to apply: `compatible`, `minor` or `breaking`.
+    #[structopt(short, long, default_value = "compatible", possible_values = &["compatible", "minor", "breaking"])]
+    level: UpdateType,
+    /// Limits how many tags will be fetched from DockerHub before stopping the search.
+    #[structopt(short, long, default_value = "100")]
+    search_limit: usize,
+    /// A configuration file defining additional pattern presets and ignored tags.
+    #[structopt(short, long, parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Only considers tags pushed at least this many days ago, e.g., `7d`. Annotations can override this with `--min-age`.
+    #[structopt(long)]
+    min_age: Option<MinAge>,
+    /// Prints the changes as a unified diff instead of writing them. Exits with 1 if there are changes and 0 otherwise.
+    #[structopt(long)]
+    diff: bool,
+}
+
+/// Finds problems with the images in Dockerfiles and docker-compose files without accessing the network.
+///
+/// Reports images without annotations, floating tags such as `latest` or a bare major version like `18`, and
+/// annotations whose pattern does not match the current tag.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    after_help = r#"Files ending in `.yml` or `.yaml` are read as docker-compose files, together with the Dockerfiles in the `build` folders of their services. All other files are read as Dockerfiles.
+
+Each problem is printed on its own line, starting with its location, e.g., `Dockerfile:2:6: <problem>`. The exit code is 1 if any problems are found, and 0 otherwise."#
+)]
+struct LintOpts {
+    /// The Dockerfiles and docker-compose files to lint.
+    #[structopt(parse(from_os_str), required = true)]
+    files: Vec<PathBuf>,
+    /// A configuration file defining additional pattern presets.
+    #[structopt(short, long, parse(from_os_str))]
+    config: Option<PathBuf>,
+}
+
+/// Checks the annotations in Dockerfiles and docker-compose files without accessing the network.
+///
+/// Reports annotations that are missing or invalid, and current tags that do not match their pattern.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    after_help = r#"Files ending in `.yml` or `.yaml` are read as docker-compose files, together with the Dockerfiles in the `build` folders of their services. All other files are read as Dockerfiles.
+
+Each problem is printed on its own line, starting with its location, e.g., `Dockerfile:2:6: <problem>`. The exit code is 1 if any problems are found, and 0 otherwise."#
+)]
+struct ValidateOpts {
+    /// The Dockerfiles and docker-compose files to validate.
+    #[structopt(parse(from_os_str), required = true)]
+    files: Vec<PathBuf>,
+    /// A configuration file defining additional pattern presets.
+    #[structopt(short, long, parse(from_os_str))]
+    config: Option<PathBuf>,
+}
+
+/// Inserts annotations for the images in a Dockerfile or docker-compose file that are not annotated yet.
+///
+/// The pattern of each image is inferred from its current tag, where every number becomes a version part and the first
+/// one is breaking, e.g., `<!>.<>.<>-slim` for `14.5.0-slim`.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    after_help = r#"Files ending in `.yml` or `.yaml` are read as docker-compose files, all other files as Dockerfiles. Annotations are inserted above the `FROM`, `COPY` or `RUN` instruction using an image, above the `ARG` or `ENV` holding its tag, or above the `image` field or `docker-image://` additional context of a service. The Dockerfiles of a docker-compose file's `build` folders are annotated as well. Images whose tag contains no numbers, such as `latest`, or whose variables are not set are skipped and listed.
+
+By default, each annotation is confirmed interactively. Press enter to accept the inferred pattern, type `n` to leave the image out, or type another pattern or preset name to use instead. Pass `--yes` to accept all inferred patterns."#
+)]
+struct AnnotateOpts {
+    /// The Dockerfile or docker-compose file to annotate.
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+    /// Accepts all inferred patterns without asking.
+    #[structopt(short, long)]
+    yes: bool,
+    /// A configuration file defining additional pattern presets.
+    #[structopt(short, long, parse(from_os_str))]
+    config: Option<PathBuf>,
+}
+
+/// Proposes patterns for an image based on its current tag.
+///
+/// The candidates are inferred from the shape of the current tag and ranked by
+/// how many of the latest tags they match and how well those tags are ordered.
+#[derive(Debug, StructOpt)]
+struct SuggestPatternOpts {
+    /// The image and current tag, e.g., `node:14.5.0-slim`.
+    image: String,
+    /// Limits how many tags will be fetched from DockerHub.
+    #[structopt(short, long, default_value = "100")]
+    search_limit: usize,
 }
 
 fn main() {
@@ -122,6 +346,12 @@ fn main() {
         Fetch(opts) => fetch(*opts),
         Check(opts) => check(opts),
         CheckCompose(opts) => check_compose(opts),
+        SuggestPattern(opts) => suggest_pattern(opts),
+        Update(opts) => update(opts),
+        UpdateCompose(opts) => update_compose(opts),
+        Lint(opts) => lint(opts),
+        Validate(opts) => validate(opts),
+        Annotate(opts) => annotate(opts),
     };
 
     match result {
@@ -138,7 +368,12 @@ struct ExitCode(i32);
 const EXIT_OK: ExitCode = ExitCode(0);
 const EXIT_NO_UPDATE: ExitCode = ExitCode(0);
 const EXIT_COMPATIBLE_UPDATE: ExitCode = ExitCode(1);
-const EXIT_BREAKING_UPDATE: ExitCode = ExitCode(2);
+const EXIT_MINOR_UPDATE: ExitCode = ExitCode(2);
+const EXIT_BREAKING_UPDATE: ExitCode = ExitCode(3);
+const EXIT_DIFF: ExitCode = ExitCode(1);
+/// `lint` or `validate` found problems in the files.
+const EXIT_PROBLEMS: ExitCode = ExitCode(1);
+/// uptag failed to run, e.g., because a file could not be read, or images failed to be checked.
 const EXIT_ERROR: ExitCode = ExitCode(10);
 
 impl ExitCode {
@@ -147,6 +382,7 @@ impl ExitCode {
         match level {
             Failure => EXIT_ERROR,
             BreakingUpdate => EXIT_BREAKING_UPDATE,
+            MinorUpdate => EXIT_MINOR_UPDATE,
             CompatibleUpdate => EXIT_COMPATIBLE_UPDATE,
             NoUpdates => EXIT_NO_UPDATE,
         }
@@ -157,12 +393,44 @@ impl ExitCode {
     }
 }
 
+fn load_config(path: &Option<PathBuf>) -> Result<Config> {
+    match path {
+        Some(path) => {
+            let input = fs::read_to_string(path).with_context(|| {
+                format!("Failed to read configuration file `{}`", clean_path(path))
+            })?;
+            Config::parse(&input).with_context(|| {
+                format!("Failed to parse configuration file `{}`", clean_path(path))
+            })
+        }
+        None => Ok(Config::default()),
+    }
+}
+
 fn fetch(opts: FetchOpts) -> Result<ExitCode> {
+    let config = load_config(&opts.config)?;
+    let extractor = match (opts.pattern, opts.regex) {
+        (Some(raw_pattern), _) => Some(
+            config
+                .presets
+                .parse(&raw_pattern)
+                .map(VersionExtractor::new)
+                .with_context(|| format!("The pattern `{}` is invalid", raw_pattern))?,
+        ),
+        (None, Some(raw_regex)) => Some(
+            VersionExtractor::from_regex(&raw_regex)
+                .with_context(|| format!("The regex `{}` is invalid", raw_regex))?,
+        ),
+        (None, None) => None,
+    };
+
     let adjusted_search_limit = std::cmp::max(opts.search_limit, opts.amount);
     let fetcher = DockerHubTagFetcher::with_search_limit(adjusted_search_limit);
-    let tags = fetcher.fetch(&opts.image);
+    let tags = fetcher
+        .fetch(&opts.image)
+        .map(|tag_result| tag_result.map(|tag_info| tag_info.name));
 
-    let result = if let Some(extractor) = opts.pattern {
+    let result = if let Some(extractor) = extractor {
         let mut tag_count = 0;
         let result: Vec<String> = tags
             .filter_map(|tag_result| {
@@ -184,7 +452,7 @@ fn fetch(opts: FetchOpts) -> Result<ExitCode> {
             "Fetched {} tags. Found {} matching `{}`:",
             tag_count,
             result.len(),
-            extractor.pattern()
+            extractor
         );
         result
     } else {
@@ -201,7 +469,47 @@ fn fetch(opts: FetchOpts) -> Result<ExitCode> {
     Ok(EXIT_OK)
 }
 
+fn suggest_pattern(opts: SuggestPatternOpts) -> Result<ExitCode> {
+    let image = opts
+        .image
+        .parse::<Image>()
+        .map_err(|_| anyhow!("The image `{}` is invalid", opts.image))?;
+    if suggest::candidates(&image.tag).is_empty() {
+        bail!(
+            "The tag `{}` contains no numbers to infer a pattern from",
+            image.tag
+        );
+    }
+
+    let fetcher = DockerHubTagFetcher::with_search_limit(opts.search_limit);
+    let tags = fetcher
+        .fetch(&image.name)
+        .map(|tag_result| tag_result.map(|tag_info| tag_info.name))
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to fetch tags")?;
+    let suggestions = suggest::suggest(&image.tag, &tags);
+
+    println!(
+        "Fetched {} tags. Candidate patterns for `{}`:",
+        tags.len(),
+        image
+    );
+    for (index, suggestion) in suggestions.iter().enumerate() {
+        println!(
+            "{rank}. `{pattern}` matches {matches} tag(s), {ordering:.0}% in order\n   e.g. {samples}",
+            rank = index + 1,
+            pattern = suggestion.pattern,
+            matches = suggestion.matches,
+            ordering = suggestion.ordering * 100.0,
+            samples = suggestion.samples.join(", ")
+        );
+    }
+
+    Ok(EXIT_OK)
+}
+
 fn check(opts: CheckOpts) -> Result<ExitCode> {
+    let config = load_config(&opts.config)?;
     let file_path = opts
         .file
         .canonicalize()
@@ -213,17 +521,26 @@ fn check(opts: CheckOpts) -> Result<ExitCode> {
         )
     })?;
 
-    let fetcher = DockerHubTagFetcher::with_search_limit(opts.search_limit);
-    let images = dockerfile::parse(&input);
-    let updates = images.map(|(image, pattern_result)| {
+    let options = UpdateOptions {
+        all_versions: opts.all_versions,
+        min_age: opts.min_age,
+        ..UpdateOptions::default()
+    };
+    let images = dockerfile::parse(&opts.file, &input, &config.presets);
+    let updates = images.map(|(reference, pattern_result)| {
         let results = pattern_result
             .map_err(UpdateError::Check)
-            .and_then(|pattern| {
-                let extractor = VersionExtractor::new(pattern);
-
-                uptag::find_update(&fetcher, &image, &extractor).map_err(UpdateError::FindUpdate)
+            .and_then(|annotation| {
+                find_annotated_update(
+                    DockerHubTagFetcher::with_search_limit,
+                    &reference.image,
+                    &annotation,
+                    opts.search_limit,
+                    &config,
+                    &options,
+                )
             });
-        (image, results)
+        (reference, results)
     });
 
     let dockerfile_report = DockerfileReport::from(updates);
@@ -242,6 +559,607 @@ fn check(opts: CheckOpts) -> Result<ExitCode> {
     Ok(exit_code)
 }
 
+fn update(opts: UpdateOpts) -> Result<ExitCode> {
+    let config = load_config(&opts.config)?;
+    let mut updater = Updater {
+        level: opts.level,
+        search_limit: opts.search_limit,
+        config: &config,
+        options: UpdateOptions {
+            min_age: opts.min_age,
+            ..UpdateOptions::default()
+        },
+        fetcher: DockerHubTagFetcher::with_search_limit,
+        failures: Vec::new(),
+    };
+
+    let (file, _) = updater.rewrite_dockerfile(&opts.file, &[])?;
+    updater.apply(vec![file], opts.diff)
+}
+
+fn update_compose(opts: UpdateComposeOpts) -> Result<ExitCode> {
+    let config = load_config(&opts.config)?;
+    let mut updater = Updater {
+        level: opts.level,
+        search_limit: opts.search_limit,
+        config: &config,
+        options: UpdateOptions {
+            min_age: opts.min_age,
+            ..UpdateOptions::default()
+        },
+        fetcher: DockerHubTagFetcher::with_search_limit,
+        failures: Vec::new(),
+    };
+
+    let files = updater.rewrite_compose(&opts.file)?;
+    updater.apply(files, opts.diff)
+}
+
+/// Finds the updates of images and applies them to their files.
+struct Updater<'a, T> {
+    /// The most severe type of update to apply.
+    level: UpdateType,
+    search_limit: usize,
+    config: &'a Config,
+    options: UpdateOptions,
+    /// Creates the fetcher of the tags with the search limit.
+    fetcher: fn(usize) -> T,
+    /// The images that failed to be checked.
+    failures: Vec<String>,
+}
+
+/// The content of a file before and after its tags are rewritten.
+struct RewrittenFile {
+    path: PathBuf,
+    original: String,
+    rewritten: String,
+    rewrites: Vec<Rewrite>,
+}
+
+impl<'a, T> Updater<'a, T>
+where
+    T: TagFetcher,
+    T::FetchError: 'static,
+{
+    /// Rewrites the tags in the docker-compose `file`, its `.env` file and the Dockerfiles of its builds.
+    ///
+    /// Tags held in build arguments are rewritten where the arguments are set, and are verified by parsing the
+    /// Dockerfiles with the rewritten arguments.
+    fn rewrite_compose(&mut self, file: &path::Path) -> Result<Vec<RewrittenFile>> {
+        let input = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read file `{}`", clean_path(file)))?;
+        let environment = load_environment(file)?;
+        let presets = &self.config.presets;
+        let parse = |input: &str, environment: &Environment| {
+            docker_compose::parse_with_environment(file, input, presets, environment)
+                .context("Failed to parse docker-compose file")
+        };
+        let services = parse(&input, &environment)?;
+
+        let compose_dir = file.parent().unwrap();
+        let (references, builds) = split_services(services, compose_dir);
+
+        // The Dockerfiles come first, since their tags may be held in the build arguments of the docker-compose file.
+        let mut files = Vec::new();
+        let mut arg_rewrites = Vec::new();
+        for (path, build) in &builds {
+            match self.rewrite_dockerfile(path, &build.args) {
+                Ok((file, external)) => {
+                    files.push((file, &build.args));
+                    arg_rewrites.extend(external);
+                }
+                Err(error) if error.is::<std::io::Error>() => {
+                    self.failures.push(format!("{:#}", error))
+                }
+                Err(error) => return Err(error),
+            }
+        }
+
+        let (original, rewrites) = self.find_rewrites(references);
+        // Tags held in the `.env` file are rewritten there.
+        let (dotenv_rewrites, compose_rewrites) = [rewrites.clone(), arg_rewrites.clone()]
+            .concat()
+            .into_iter()
+            .partition::<Vec<_>, _>(|rewrite| {
+                environment
+                    .dotenv
+                    .as_ref()
+                    .is_some_and(|dotenv| rewrite.reference.location.file == dotenv.file)
+            });
+        let rewritten = rewrite::rewrite(&input, &compose_rewrites)
+            .with_context(|| format!("Failed to rewrite the tags in `{}`", clean_path(file)))?;
+        let mut rewritten_environment = environment.clone();
+        if let Some(dotenv) = &environment.dotenv {
+            let rewritten_dotenv =
+                rewrite::rewrite(&dotenv.input, &dotenv_rewrites).with_context(|| {
+                    format!(
+                        "Failed to rewrite the tags in `{}`",
+                        clean_path(&dotenv.file)
+                    )
+                })?;
+            rewritten_environment = environment.with_dotenv(rewritten_dotenv);
+        }
+        let (rewritten_references, rewritten_builds) =
+            split_services(parse(&rewritten, &rewritten_environment)?, compose_dir);
+        rewrite::verify(&rewrites, &original, &rewritten_references).with_context(|| {
+            format!(
+                "Failed to verify the rewritten tags in `{}`, so no file was changed",
+                clean_path(file)
+            )
+        })?;
+        for (dockerfile, args) in &files {
+            let rewritten_args = rewritten_builds
+                .iter()
+                .find(|(path, _)| *path == dockerfile.path)
+                .map_or(&[][..], |(_, build)| &build.args);
+            let original =
+                dockerfile::parse_with_args(&dockerfile.path, &dockerfile.original, presets, args)
+                    .map(|(reference, _)| reference)
+                    .collect::<Vec<_>>();
+            let rewritten = dockerfile::parse_with_args(
+                &dockerfile.path,
+                &dockerfile.rewritten,
+                presets,
+                rewritten_args,
+            )
+            .collect::<Vec<_>>();
+            rewrite::verify(&arg_rewrites, &original, &rewritten).with_context(|| {
+                format!(
+                    "Failed to verify the rewritten build arguments of `{}`, so no file was changed",
+                    clean_path(&dockerfile.path)
+                )
+            })?;
+        }
+
+        let mut files = files.into_iter().map(|(file, _)| file).collect::<Vec<_>>();
+        files.insert(
+            0,
+            RewrittenFile {
+                path: file.to_path_buf(),
+                original: input,
+                rewritten,
+                rewrites: compose_rewrites,
+            },
+        );
+        if let (Some(dotenv), Some(rewritten_dotenv)) =
+            (environment.dotenv, rewritten_environment.dotenv)
+        {
+            files.insert(
+                1,
+                RewrittenFile {
+                    path: dotenv.file,
+                    original: dotenv.input,
+                    rewritten: rewritten_dotenv.input,
+                    rewrites: dotenv_rewrites,
+                },
+            );
+        }
+        Ok(files)
+    }
+
+    /// Finds the new tags for the `references`, returning all references alongside the new tags.
+    ///
+    /// Images that fail to be checked are collected in `failures`.
+    fn find_rewrites(
+        &mut self,
+        references: impl IntoIterator<Item = (ImageReference, Result<Annotation, CheckError>)>,
+    ) -> (Vec<ImageReference>, Vec<Rewrite>) {
+        let mut original = Vec::new();
+        let mut rewrites = Vec::new();
+        for (reference, pattern_result) in references {
+            original.push(reference.clone());
+            let result = pattern_result
+                .map_err(UpdateError::Check)
+                .and_then(|annotation| {
+                    find_annotated_update(
+                        self.fetcher,
+                        &reference.image,
+                        &annotation,
+                        self.search_limit,
+                        self.config,
+                        &self.options,
+                    )
+                });
+            match result {
+                Ok(update) => {
+                    if let Some(tag) = update.newest_up_to(self.level) {
+                        rewrites.push(Rewrite {
+                            tag: tag.clone(),
+                            reference,
+                        });
+                    }
+                }
+                Err(error) => {
+                    self.failures
+                        .push(format!("{}: {}", reference, display_error(&error)))
+                }
+            }
+        }
+        (original, rewrites)
+    }
+
+    /// Rewrites the tags in the Dockerfile at `path`, and checks that they still match their patterns.
+    ///
+    /// Tags held in the `build_args` are not written to the Dockerfile, but returned as the second element.
+    fn rewrite_dockerfile(
+        &mut self,
+        path: &path::Path,
+        build_args: &[BuildArg],
+    ) -> Result<(RewrittenFile, Vec<Rewrite>)> {
+        let input = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read file `{}`", clean_path(path)))?;
+        let presets = &self.config.presets;
+
+        let (original, rewrites) = self.find_rewrites(dockerfile::parse_with_args(
+            path, &input, presets, build_args,
+        ));
+        let (rewrites, external): (Vec<_>, Vec<_>) = rewrites
+            .into_iter()
+            .partition(|rewrite| rewrite.reference.location.file == path);
+        let rewritten = rewrite::rewrite(&input, &rewrites)
+            .with_context(|| format!("Failed to rewrite the tags in `{}`", clean_path(path)))?;
+        let rewritten_references =
+            dockerfile::parse_with_args(path, &rewritten, presets, build_args).collect::<Vec<_>>();
+        rewrite::verify(&rewrites, &original, &rewritten_references).with_context(|| {
+            format!(
+                "Failed to verify the rewritten tags in `{}`, so no file was changed",
+                clean_path(path)
+            )
+        })?;
+
+        let file = RewrittenFile {
+            path: path.to_path_buf(),
+            original: input,
+            rewritten,
+            rewrites,
+        };
+        Ok((file, external))
+    }
+
+    /// Writes the changed `files` and reports the applied updates and failures.
+    ///
+    /// With `diff`, the changes are printed as a unified diff instead of being written.
+    fn apply(self, files: Vec<RewrittenFile>, diff: bool) -> Result<ExitCode> {
+        if !self.failures.is_empty() {
+            eprintln!(
+                "{} failure(s):\n{}\n",
+                self.failures.len(),
+                self.failures.join("\n")
+            );
+        }
+
+        let changed = files
+            .iter()
+            .filter(|file| file.rewritten != file.original)
+            .collect::<Vec<_>>();
+        if diff {
+            for file in &changed {
+                print!(
+                    "{}",
+                    rewrite::unified_diff(&diff_path(&file.path), &file.original, &file.rewritten)
+                );
+            }
+        } else {
+            if changed.is_empty() {
+                println!("No updates to apply.");
+            }
+            for file in &changed {
+                rewrite::write_atomically(&file.path, &file.rewritten).with_context(|| {
+                    format!("Failed to write file `{}`", clean_path(&file.path))
+                })?;
+
+                println!(
+                    "Applied {} update(s) to `{}`:",
+                    file.rewrites.len(),
+                    clean_path(&file.path)
+                );
+                for rewrite in &file.rewrites {
+                    println!("{}\n  -> {}", rewrite.reference, rewrite.tag);
+                }
+            }
+        }
+
+        if !self.failures.is_empty() {
+            Ok(EXIT_ERROR)
+        } else if diff && !changed.is_empty() {
+            Ok(EXIT_DIFF)
+        } else {
+            Ok(EXIT_OK)
+        }
+    }
+}
+
+/// The `path` as it appears in a diff, using `/` as separator and without `.` components or a leading root.
+fn diff_path(path: &path::Path) -> String {
+    path.components()
+        .filter(|component| {
+            !matches!(
+                component,
+                path::Component::CurDir | path::Component::RootDir
+            )
+        })
+        .map(|component| component.as_os_str().to_string_lossy())
+        .join("/")
+}
+
+fn lint(opts: LintOpts) -> Result<ExitCode> {
+    let config = load_config(&opts.config)?;
+
+    let mut problems = Vec::new();
+    for file in &opts.files {
+        let references = read_references(file, &config, &mut problems)?;
+        problems.extend(
+            uptag::lint::lint(references)
+                .iter()
+                .map(ToString::to_string),
+        );
+    }
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if problems.is_empty() {
+        Ok(EXIT_OK)
+    } else {
+        Ok(EXIT_PROBLEMS)
+    }
+}
+
+fn validate(opts: ValidateOpts) -> Result<ExitCode> {
+    let config = load_config(&opts.config)?;
+
+    let mut problems = Vec::new();
+    for file in &opts.files {
+        for (reference, annotation) in read_references(file, &config, &mut problems)? {
+            let result = annotation
+                .and_then(|annotation| dockerfile::validate(&reference.image, &annotation));
+            if let Err(error) = result {
+                problems.push(format!("{}: {}", reference.location, display_error(&error)));
+            }
+        }
+    }
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if problems.is_empty() {
+        Ok(EXIT_OK)
+    } else {
+        Ok(EXIT_PROBLEMS)
+    }
+}
+
+fn annotate(opts: AnnotateOpts) -> Result<ExitCode> {
+    let config = load_config(&opts.config)?;
+    let input = fs::read_to_string(&opts.file)
+        .with_context(|| format!("Failed to read file `{}`", clean_path(&opts.file)))?;
+
+    // The Dockerfiles of the services' builds are annotated as well, since their images are checked with the
+    // docker-compose file.
+    let mut files = Vec::new();
+    if is_compose_file(&opts.file) {
+        let environment = load_environment(&opts.file)?;
+        let annotations =
+            uptag::annotate::docker_compose(&opts.file, &input, &config.presets, &environment)
+                .context("Failed to parse docker-compose file")?;
+        let services = docker_compose::parse_with_environment(
+            &opts.file,
+            &input,
+            &config.presets,
+            &environment,
+        )
+        .context("Failed to parse docker-compose file")?;
+        files.push((opts.file.clone(), input, annotations));
+
+        let (_, builds) = split_services(services, opts.file.parent().unwrap());
+        for (path, build) in builds {
+            let input = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read file `{}`", clean_path(&path)))?;
+            let annotations =
+                uptag::annotate::dockerfile_with_args(&path, &input, &config.presets, &build.args);
+            files.push((path, input, annotations));
+        }
+    } else {
+        let annotations = uptag::annotate::dockerfile(&opts.file, &input, &config.presets);
+        files.push((opts.file.clone(), input, annotations));
+    }
+
+    let mut inserted = 0;
+    let mut skipped = Vec::new();
+    for (path, input, annotations) in files {
+        skipped.extend(annotations.skipped);
+        let mut accepted = Vec::new();
+        for mut insertion in annotations.insertions {
+            if !opts.yes {
+                match confirm_pattern(&insertion, &config)? {
+                    Some(pattern) => insertion.pattern = pattern,
+                    None => continue,
+                }
+            }
+            accepted.push(insertion);
+        }
+        if accepted.is_empty() {
+            continue;
+        }
+
+        rewrite::write_atomically(&path, &uptag::annotate::insert(&input, &accepted))
+            .with_context(|| format!("Failed to write file `{}`", clean_path(&path)))?;
+        println!(
+            "Inserted {} annotation(s) into `{}`:",
+            accepted.len(),
+            clean_path(&path)
+        );
+        for insertion in &accepted {
+            println!("{}\n  {}", insertion.reference, insertion.comment().trim());
+        }
+        inserted += accepted.len();
+    }
+
+    if inserted == 0 {
+        println!("No annotations to insert.");
+    }
+    if !skipped.is_empty() {
+        println!(
+            "\nSkipped {} image(s), which have to be annotated by hand:",
+            skipped.len()
+        );
+        for (reference, skip) in &skipped {
+            println!("{}: {}", reference, display_error(skip));
+        }
+    }
+    Ok(EXIT_OK)
+}
+
+fn confirm_pattern(insertion: &Insertion, config: &Config) -> Result<Option<String>> {
+    let tag = &insertion.tag;
+    println!(
+        "{}\nAnnotate with pattern `{}`? [Y/n/<pattern>]",
+        insertion.reference, insertion.pattern
+    );
+    loop {
+        let mut answer = String::new();
+        let read = std::io::stdin()
+            .read_line(&mut answer)
+            .context("Failed to read answer")?;
+        let answer = answer.trim();
+        match answer {
+            _ if read == 0 => return Ok(None),
+            "" | "y" | "Y" | "yes" => return Ok(Some(insertion.pattern.clone())),
+            "n" | "N" | "no" => return Ok(None),
+            raw_pattern => match config.presets.parse(raw_pattern).map(VersionExtractor::new) {
+                Ok(extractor) if extractor.matches(tag) => {
+                    return Ok(Some(raw_pattern.to_string()))
+                }
+                Ok(_) => println!(
+                    "The pattern `{}` does not match `{}`. Try again:",
+                    raw_pattern, tag
+                ),
+                Err(error) => println!(
+                    "The pattern `{}` is invalid: {}. Try again:",
+                    raw_pattern,
+                    display_error(&error)
+                ),
+            },
+        }
+    }
+}
+
+/// Reads the images of the Dockerfile or docker-compose `file`, including the Dockerfiles in the `build` folders of its services.
+///
+/// A Dockerfile built by several services is read once, with the build arguments of the first one.
+///
+/// Files of services that cannot be read or parsed are described in `problems`.
+fn read_references(
+    file: &path::Path,
+    config: &Config,
+    problems: &mut Vec<String>,
+) -> Result<Vec<(ImageReference, Result<Annotation, CheckError>)>> {
+    let input = fs::read_to_string(file)
+        .with_context(|| format!("Failed to read file `{}`", clean_path(file)))?;
+    if !is_compose_file(file) {
+        return Ok(dockerfile::parse(file, &input, &config.presets).collect());
+    }
+
+    let environment = load_environment(file)?;
+    let services =
+        match docker_compose::parse_with_environment(file, &input, &config.presets, &environment) {
+            Ok(services) => services,
+            Err(error) => {
+                problems.push(format!("{}: {}", file.display(), display_error(&error)));
+                return Ok(Vec::new());
+            }
+        };
+    let (mut references, builds) = split_services(services, file.parent().unwrap());
+    for (path, build) in builds {
+        match fs::read_to_string(&path) {
+            Ok(input) => references.extend(dockerfile::parse_with_args(
+                &path,
+                &input,
+                &config.presets,
+                &build.args,
+            )),
+            Err(error) => problems.push(format!(
+                "{}: Failed to read file: {}",
+                path.display(),
+                error
+            )),
+        }
+    }
+    Ok(references)
+}
+
+/// Splits the `services` into the images they use and the Dockerfiles they build, each built Dockerfile listed once.
+#[allow(clippy::type_complexity)]
+fn split_services(
+    services: Vec<docker_compose::ServiceResult>,
+    compose_dir: &path::Path,
+) -> (
+    Vec<(ImageReference, Result<Annotation, CheckError>)>,
+    Vec<(PathBuf, docker_compose::Build)>,
+) {
+    let mut references = Vec::new();
+    let mut builds: Vec<(PathBuf, docker_compose::Build)> = Vec::new();
+    for (_, build_context) in services {
+        match build_context {
+            BuildContext::Image(reference, annotation) => references.push((reference, annotation)),
+            BuildContext::Folder(build, ()) => {
+                let path = build.dockerfile_path(compose_dir);
+                if !builds.iter().any(|(known, _)| *known == path) {
+                    builds.push((path, build));
+                }
+            }
+        }
+    }
+    (references, builds)
+}
+
+/// Loads the variables that the docker-compose `file` is interpolated with, including the `.env` file next to it.
+fn load_environment(file: &path::Path) -> Result<Environment> {
+    let project_dir = file.parent().unwrap();
+    Environment::load(project_dir).with_context(|| {
+        format!(
+            "Failed to read file `{}`",
+            clean_path(&project_dir.join(".env"))
+        )
+    })
+}
+
+/// Whether the file at `path` is read as a docker-compose file instead of a Dockerfile.
+fn is_compose_file(path: &path::Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "yml" || extension == "yaml")
+}
+
+/// Finds the update of an image with the options of its annotation and the configuration.
+///
+/// The `fetcher` is created with the search limit of the annotation, or `search_limit` if it has none. The current
+/// tag is validated against the pattern before any tags are fetched.
+fn find_annotated_update<T>(
+    fetcher: fn(usize) -> T,
+    image: &Image,
+    annotation: &Annotation,
+    search_limit: usize,
+    config: &Config,
+    options: &UpdateOptions,
+) -> Result<Update, UpdateError<T::FetchError>>
+where
+    T: TagFetcher,
+    T::FetchError: 'static,
+{
+    dockerfile::validate(image, annotation)?;
+    let fetcher = fetcher(annotation.search_limit.unwrap_or(search_limit));
+    let update = uptag::find_update_with(
+        &fetcher,
+        image,
+        &annotation.extractor,
+        &annotation.options(&config.update_options(&image.name, options)),
+    )?;
+    Ok(Update {
+        note: annotation.note.clone(),
+        ..update
+    })
+}
+
 #[derive(Debug, Error)]
 enum UpdateError<E>
 where
@@ -260,6 +1178,7 @@ where
 }
 
 fn check_compose(opts: CheckComposeOpts) -> Result<ExitCode> {
+    let config = load_config(&opts.config)?;
     let compose_file_path = opts
         .file
         .canonicalize()
@@ -270,11 +1189,21 @@ fn check_compose(opts: CheckComposeOpts) -> Result<ExitCode> {
             display_canonicalized(&compose_file_path)
         )
     })?;
-    let services =
-        docker_compose::parse(&compose_file).context("Failed to parse docker-compose file")?;
+    let environment = load_environment(&opts.file)?;
+    let services = docker_compose::parse_with_environment(
+        &opts.file,
+        &compose_file,
+        &config.presets,
+        &environment,
+    )
+    .context("Failed to parse docker-compose file")?;
 
     let compose_dir = opts.file.parent().unwrap();
-    let fetcher = DockerHubTagFetcher::with_search_limit(opts.search_limit);
+    let options = UpdateOptions {
+        all_versions: opts.all_versions,
+        min_age: opts.min_age,
+        ..UpdateOptions::default()
+    };
 
     let progress_bar = ProgressBar::new(services.len() as u64)
         .with_style(ProgressStyle::default_bar().template("{msg}\n{wide_bar} {pos}/{len}"));
@@ -287,14 +1216,23 @@ fn check_compose(opts: CheckComposeOpts) -> Result<ExitCode> {
         progress_bar.inc(1);
 
         match build_context {
-            docker_compose::BuildContext::Image(image, pattern) => {
-                let extractor = VersionExtractor::new(pattern);
-                let update = uptag::find_update(&fetcher, &image, &extractor)
-                    .map_err(UpdateError::FindUpdate);
+            docker_compose::BuildContext::Image(image, annotation) => {
+                let update = annotation
+                    .map_err(UpdateError::Check)
+                    .and_then(|annotation| {
+                        find_annotated_update(
+                            DockerHubTagFetcher::with_search_limit,
+                            &image.image,
+                            &annotation,
+                            opts.search_limit,
+                            &config,
+                            &options,
+                        )
+                    });
                 (service_name, BuildContext::Image(image, update))
             }
-            docker_compose::BuildContext::Folder(relative_path, ()) => {
-                let path = compose_dir.join(relative_path).join("Dockerfile");
+            docker_compose::BuildContext::Folder(build, ()) => {
+                let path = build.dockerfile_path(compose_dir);
                 let path_display = path
                     .canonicalize()
                     .map(|path| display_canonicalized(&path))
@@ -306,18 +1244,27 @@ fn check_compose(opts: CheckComposeOpts) -> Result<ExitCode> {
                         source: error,
                     })
                     .map(|input| {
-                        let images = dockerfile::parse(&input);
-                        let updates = images.map(|(image, pattern_result)| {
+                        let images = dockerfile::parse_with_args(
+                            &path,
+                            &input,
+                            &config.presets,
+                            &build.args,
+                        );
+                        let updates = images.map(|(reference, pattern_result)| {
                             let results =
                                 pattern_result
                                     .map_err(UpdateError::Check)
-                                    .and_then(|pattern| {
-                                        let extractor = VersionExtractor::new(pattern);
-
-                                        uptag::find_update(&fetcher, &image, &extractor)
-                                            .map_err(UpdateError::FindUpdate)
+                                    .and_then(|annotation| {
+                                        find_annotated_update(
+                                            DockerHubTagFetcher::with_search_limit,
+                                            &reference.image,
+                                            &annotation,
+                                            opts.search_limit,
+                                            &config,
+                                            &options,
+                                        )
                                     });
-                            (image, results)
+                            (reference, results)
                         });
                         updates.collect::<Vec<_>>()
                     });
@@ -391,3 +1338,119 @@ fn clean_path(path: &path::Path) -> String {
         }))
         .join(&SEPARATOR)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use uptag::tag_fetcher::TagInfo;
+
+    /// Serves the tags of a few images without accessing the network.
+    struct FakeFetcher;
+
+    impl TagFetcher for FakeFetcher {
+        type TagIter = Vec<Result<TagInfo, Self::FetchError>>;
+        type FetchError = std::io::Error;
+
+        fn fetch(&self, image: &ImageName) -> Self::TagIter {
+            let tags: &[&str] = match image.to_string().as_str() {
+                "python" => &["3.12.1-slim", "3.11.9-slim", "3.11.4-slim"],
+                "redis" => &["7.2", "7.0"],
+                "alpine" => &["3.19", "3.18"],
+                _ => &[],
+            };
+            tags.iter()
+                .map(|tag| Ok(TagInfo::new(tag.to_string())))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn reads_shared_dockerfiles_once() {
+        let dir =
+            std::env::temp_dir().join(format!("uptag-read-references-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let compose = "services:\n  app:\n    build: .\n  worker:\n    build:\n      context: .\n";
+        fs::write(dir.join("docker-compose.yml"), compose).unwrap();
+        fs::write(dir.join("Dockerfile"), "FROM node:18.17.0\n").unwrap();
+
+        let mut problems = Vec::new();
+        let result = read_references(
+            &dir.join("docker-compose.yml"),
+            &Config::default(),
+            &mut problems,
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        let references = result.unwrap();
+        assert_eq!(problems, Vec::<String>::new());
+        assert_eq!(
+            references
+                .iter()
+                .map(|(reference, _)| reference.image.to_string())
+                .collect::<Vec<_>>(),
+            vec!["node:18.17.0"]
+        );
+    }
+
+    #[test]
+    fn updates_compose_file_through_build_args_and_dotenv() {
+        let dir = std::env::temp_dir().join(format!("uptag-update-compose-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let compose = r#"services:
+  app:
+    build:
+      context: .
+      args:
+        PY_VERSION: "3.11.4"
+  cache:
+    # uptag --pattern "<!>.<>"
+    image: redis:${UPTAG_TEST_REDIS_VERSION}
+"#;
+        let dotenv = "UPTAG_TEST_REDIS_VERSION=7.0\n";
+        let dockerfile = r#"# uptag --pattern "<!>.<>.<>"
+ARG PY_VERSION=3.11.0
+# uptag --pattern "<!>.<>"
+FROM alpine:3.18
+FROM python:${PY_VERSION}-slim
+"#;
+        fs::write(dir.join("docker-compose.yml"), compose).unwrap();
+        fs::write(dir.join(".env"), dotenv).unwrap();
+        fs::write(dir.join("Dockerfile"), dockerfile).unwrap();
+
+        let config = Config::default();
+        let mut updater = Updater {
+            level: UpdateType::Minor,
+            search_limit: 100,
+            config: &config,
+            options: UpdateOptions::default(),
+            fetcher: |_| FakeFetcher,
+            failures: Vec::new(),
+        };
+        let result = updater.rewrite_compose(&dir.join("docker-compose.yml"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let files = result.unwrap();
+        assert_eq!(updater.failures, Vec::<String>::new());
+        assert_eq!(
+            files
+                .iter()
+                .map(|file| (
+                    file.path.file_name().unwrap().to_str().unwrap(),
+                    file.rewritten.as_str()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "docker-compose.yml",
+                    compose.replace("\"3.11.4\"", "\"3.12.1\"").as_str()
+                ),
+                (".env", "UPTAG_TEST_REDIS_VERSION=7.2\n"),
+                (
+                    "Dockerfile",
+                    dockerfile.replace("alpine:3.18", "alpine:3.19").as_str()
+                ),
+            ]
+        );
+    }
+}
@@ -1,14 +1,27 @@
+use indexmap::IndexMap;
 use itertools::Itertools;
+use lazy_static::lazy_static;
 use thiserror::Error;
 
+use crate::version::UpdateType;
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Pattern {
     parts: Vec<PatternPart>,
-    breaking_degree: usize,
+    /// The name of the preset this pattern was created from, if any.
+    preset: Option<String>,
 }
 
 impl Pattern {
+    /// Parses either the name of a built-in preset or the pattern syntax.
+    ///
+    /// To also resolve presets from a configuration file, use `Presets::parse`.
     pub fn parse(i: &str) -> Result<Pattern, Error> {
+        BUILTIN_PRESETS.parse(i)
+    }
+
+    /// Parses the pattern syntax, without resolving presets.
+    pub fn parse_syntax(i: &str) -> Result<Pattern, Error> {
         parser::pattern(i)
             .map(|(_, pattern)| pattern)
             .map_err(|error| Error::new(i, error))
@@ -18,8 +31,125 @@ impl Pattern {
         &self.parts
     }
 
-    pub fn breaking_degree(&self) -> usize {
-        self.breaking_degree
+    pub fn preset(&self) -> Option<&str> {
+        self.preset.as_deref()
+    }
+
+    /// Wraps the pattern in literals, e.g., to match the tag `18.17.0-slim` given a pattern for `18.17.0`.
+    pub fn surrounded_by(&self, prefix: &str, suffix: &str) -> Pattern {
+        let literal = |text: &str| {
+            Some(text)
+                .filter(|text| !text.is_empty())
+                .map(|text| PatternPart::Literal(text.to_string()))
+        };
+        let parts = literal(prefix)
+            .into_iter()
+            .chain(self.parts.iter().cloned())
+            .chain(literal(suffix))
+            .collect();
+        Pattern {
+            parts,
+            preset: None,
+        }
+    }
+
+    /// The type of update that changing the number causes, for each version part in order.
+    pub fn version_part_types(&self) -> Vec<UpdateType> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                PatternPart::VersionPart { update_type } => Some(*update_type),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The type of update that changing between alternatives causes, for each alternation in order.
+    pub fn alternation_types(&self) -> Vec<UpdateType> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                PatternPart::Alternation { update_type, .. } => Some(*update_type),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A registry of named patterns.
+///
+/// The built-in presets can be extended, e.g., by a configuration file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Presets {
+    presets: IndexMap<String, Pattern>,
+}
+
+lazy_static! {
+    /// The built-in presets, which are parsed only once.
+    static ref BUILTIN_PRESETS: Presets = {
+        let mut presets = Presets::empty();
+        for (name, raw_pattern) in &[
+            ("semver", "<!>.<>.<>"),
+            ("semver-alpine", "<!>.<>.<>-alpine"),
+            // Date-based versions such as `2023.10.01` carry no information about compatibility.
+            ("calver", "<>.<>.<>"),
+            ("major.minor", "<!>.<>"),
+        ] {
+            presets
+                .insert(name, raw_pattern)
+                .expect("Built-in presets are valid");
+        }
+        presets
+    };
+}
+
+impl Presets {
+    /// Creates a registry without any presets.
+    pub fn empty() -> Self {
+        Presets {
+            presets: IndexMap::new(),
+        }
+    }
+
+    /// Creates a registry containing the built-in presets.
+    pub fn builtin() -> Self {
+        BUILTIN_PRESETS.clone()
+    }
+
+    /// Adds a preset with the given `name`, replacing any previous preset of that name.
+    ///
+    /// The `raw_pattern` must use the pattern syntax, it cannot refer to other presets.
+    pub fn insert(&mut self, name: &str, raw_pattern: &str) -> Result<(), Error> {
+        let mut pattern = Pattern::parse_syntax(raw_pattern)?;
+        pattern.preset = Some(name.to_string());
+        self.presets.insert(name.to_string(), pattern);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Pattern> {
+        self.presets.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Pattern)> {
+        self.presets
+            .iter()
+            .map(|(name, pattern)| (name.as_str(), pattern))
+    }
+
+    /// Parses either the name of a preset in this registry or the pattern syntax.
+    ///
+    /// Preset names take precedence over literal patterns of the same text.
+    pub fn parse(&self, i: &str) -> Result<Pattern, Error> {
+        match self.get(i) {
+            Some(pattern) => Ok(pattern.clone()),
+            None => Pattern::parse_syntax(i),
+        }
+    }
+}
+
+impl Default for Presets {
+    fn default() -> Self {
+        Self::builtin()
     }
 }
 
@@ -42,7 +172,10 @@ impl Error {
 
 impl std::fmt::Display for Pattern {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut version_part_counter = 0;
+        if let Some(preset) = &self.preset {
+            return write!(f, "{}", preset);
+        }
+
         write!(
             f,
             "{}",
@@ -51,15 +184,28 @@ impl std::fmt::Display for Pattern {
                 .map(|part| {
                     use PatternPart::*;
                     match part {
-                        VersionPart => {
-                            version_part_counter += 1;
-                            if version_part_counter <= self.breaking_degree() {
-                                "<!>".to_string()
-                            } else {
-                                "<>".to_string()
-                            }
+                        VersionPart { update_type } => {
+                            format!("<{}>", update_type_marker(*update_type))
                         }
                         Literal(literal) => literal.clone(),
+                        Alternation {
+                            alternatives,
+                            update_type,
+                        } => {
+                            let alternatives = alternatives.join("|");
+                            // A leading `~` would be read as the minor marker.
+                            let escape = if alternatives.starts_with('~') {
+                                "\\"
+                            } else {
+                                ""
+                            };
+                            format!(
+                                "({}{}{})",
+                                update_type_marker(*update_type),
+                                escape,
+                                alternatives
+                            )
+                        }
                     }
                 })
                 .join("")
@@ -67,10 +213,31 @@ impl std::fmt::Display for Pattern {
     }
 }
 
+fn update_type_marker(update_type: UpdateType) -> &'static str {
+    match update_type {
+        UpdateType::Breaking => "!",
+        UpdateType::Minor => "~",
+        UpdateType::Compatible => "",
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum PatternPart {
-    VersionPart,
+    /// Matches a number.
+    ///
+    /// Changing the number causes an update of type `update_type`.
+    VersionPart {
+        update_type: UpdateType,
+    },
     Literal(String),
+    /// Matches any of the literal alternatives.
+    ///
+    /// Changing between alternatives causes an update of type `update_type`.
+    /// Later alternatives are considered newer than earlier ones.
+    Alternation {
+        alternatives: Vec<String>,
+        update_type: UpdateType,
+    },
 }
 
 mod parser {
@@ -78,10 +245,10 @@ mod parser {
 
     use nom::branch::alt;
     use nom::bytes::complete::{tag, take_while1};
-    use nom::combinator::{all_consuming, opt, recognize};
+    use nom::combinator::{all_consuming, opt, value};
     use nom::error::ParseError;
-    use nom::multi::many0;
-    use nom::sequence::tuple;
+    use nom::multi::{fold_many0, fold_many1, many0, many1};
+    use nom::sequence::{delimited, preceded, tuple};
     use nom::IResult;
 
     pub type Error<'a> = nom::Err<nom::error::VerboseError<&'a str>>;
@@ -90,86 +257,94 @@ mod parser {
     where
         E: ParseError<&'a str>,
     {
-        let (o, (maybe_first, mut breaking, mut compatible)) = all_consuming(tuple((
-            opt(outer_literal),
-            breaking_parts,
-            compatible_parts,
-        )))(i)?;
+        let (o, parts) = all_consuming(many0(alt((literal, alternation, version_part))))(i)?;
 
-        let breaking_degree = breaking
-            .iter()
-            .filter(|part| matches!(part, PatternPart::VersionPart))
-            .count();
-        let mut parts = match maybe_first {
-            Some(first) => vec![first],
-            None => vec![],
-        };
-        parts.append(&mut breaking);
-        parts.append(&mut compatible);
         Ok((
             o,
             Pattern {
                 parts,
-                breaking_degree,
+                preset: None,
             },
         ))
     }
 
-    pub fn breaking_parts<'a, E>(i: &'a str) -> IResult<&'a str, Vec<PatternPart>, E>
+    pub fn literal<'a, E>(i: &'a str) -> IResult<&'a str, PatternPart, E>
     where
         E: ParseError<&'a str>,
     {
-        many0(alt((inner_literal, breaking_version_part)))(i)
+        let (o, literal) = fold_many1(literal_chunk, String::new, push_str)(i)?;
+        Ok((o, PatternPart::Literal(literal)))
     }
 
-    pub fn compatible_parts<'a, E>(i: &'a str) -> IResult<&'a str, Vec<PatternPart>, E>
+    /// A literal that may be empty, as used for alternatives.
+    fn alternative<'a, E>(i: &'a str) -> IResult<&'a str, String, E>
     where
         E: ParseError<&'a str>,
     {
-        many0(alt((inner_literal, compatible_version_part)))(i)
+        fold_many0(literal_chunk, String::new, push_str)(i)
     }
 
-    pub fn inner_literal<'a, E>(i: &'a str) -> IResult<&'a str, PatternPart, E>
+    /// Literal characters, or an escaped `\~`, which is needed to start the first alternative of an alternation
+    /// with `~` instead of marking the alternation as minor.
+    fn literal_chunk<'a, E>(i: &'a str) -> IResult<&'a str, &'a str, E>
     where
         E: ParseError<&'a str>,
     {
-        let (o, literal) = take_while1(is_inner_literal)(i)?;
-        Ok((o, PatternPart::Literal(literal.to_string())))
+        alt((take_while1(is_literal), value("~", tag("\\~"))))(i)
     }
 
-    pub fn outer_literal<'a, E>(i: &'a str) -> IResult<&'a str, PatternPart, E>
-    where
-        E: ParseError<&'a str>,
-    {
-        let (o, literal) = recognize(tuple((
-            take_while1(is_outer_literal),
-            take_while1(is_inner_literal),
-        )))(i)?;
-        Ok((o, PatternPart::Literal(literal.to_string())))
+    fn push_str(mut text: String, chunk: &str) -> String {
+        text.push_str(chunk);
+        text
     }
 
-    pub fn is_inner_literal(c: char) -> bool {
-        is_outer_literal(c) || c == '.' || c == '-'
+    /// Accepts all characters that are valid in an OCI tag (`[A-Za-z0-9_.-]`),
+    /// as well as `+` and `~`, which other versioning schemes use, e.g., for build metadata.
+    pub fn is_literal(c: char) -> bool {
+        c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '+' | '~')
     }
 
-    pub fn is_outer_literal(c: char) -> bool {
-        c.is_ascii_alphanumeric() || c == '_'
+    pub fn alternation<'a, E>(i: &'a str) -> IResult<&'a str, PatternPart, E>
+    where
+        E: ParseError<&'a str>,
+    {
+        let (o, (_, update_type, first, mut rest, _)) = tuple((
+            tag("("),
+            update_type_marker,
+            alternative,
+            many1(preceded(tag("|"), alternative)),
+            tag(")"),
+        ))(i)?;
+
+        rest.insert(0, first);
+        Ok((
+            o,
+            PatternPart::Alternation {
+                alternatives: rest,
+                update_type,
+            },
+        ))
     }
 
-    pub fn breaking_version_part<'a, E>(i: &'a str) -> IResult<&'a str, PatternPart, E>
+    pub fn version_part<'a, E>(i: &'a str) -> IResult<&'a str, PatternPart, E>
     where
         E: ParseError<&'a str>,
     {
-        let (o, _) = tag("<!>")(i)?;
-        Ok((o, PatternPart::VersionPart))
+        let (o, update_type) = delimited(tag("<"), update_type_marker, tag(">"))(i)?;
+        Ok((o, PatternPart::VersionPart { update_type }))
     }
 
-    pub fn compatible_version_part<'a, E>(i: &'a str) -> IResult<&'a str, PatternPart, E>
+    pub fn update_type_marker<'a, E>(i: &'a str) -> IResult<&'a str, UpdateType, E>
     where
         E: ParseError<&'a str>,
     {
-        let (o, _) = tag("<>")(i)?;
-        Ok((o, PatternPart::VersionPart))
+        let (o, marker) = opt(alt((tag("!"), tag("~"))))(i)?;
+        let update_type = match marker {
+            Some("!") => UpdateType::Breaking,
+            Some(_) => UpdateType::Minor,
+            None => UpdateType::Compatible,
+        };
+        Ok((o, update_type))
     }
 
     #[cfg(test)]
@@ -182,7 +357,7 @@ mod parser {
                 Pattern::parse("1.2.3"),
                 Ok(Pattern {
                     parts: vec![PatternPart::Literal("1.2.3".to_string())],
-                    breaking_degree: 0
+                    preset: None,
                 })
             );
         }
@@ -192,8 +367,10 @@ mod parser {
             assert_eq!(
                 Pattern::parse("<>"),
                 Ok(Pattern {
-                    parts: vec![PatternPart::VersionPart],
-                    breaking_degree: 0
+                    parts: vec![PatternPart::VersionPart {
+                        update_type: UpdateType::Compatible
+                    }],
+                    preset: None,
                 })
             )
         }
@@ -205,17 +382,220 @@ mod parser {
                 Pattern::parse("<!>.<>.<>"),
                 Ok(Pattern {
                     parts: vec![
-                        VersionPart,
+                        VersionPart {
+                            update_type: UpdateType::Breaking
+                        },
+                        Literal(".".to_string()),
+                        VersionPart {
+                            update_type: UpdateType::Compatible
+                        },
+                        Literal(".".to_string()),
+                        VersionPart {
+                            update_type: UpdateType::Compatible
+                        }
+                    ],
+                    preset: None,
+                })
+            )
+        }
+
+        #[test]
+        fn parses_leading_literal() {
+            use PatternPart::*;
+            assert_eq!(
+                Pattern::parse("v<!>.<>"),
+                Ok(Pattern {
+                    parts: vec![
+                        Literal("v".to_string()),
+                        VersionPart {
+                            update_type: UpdateType::Breaking
+                        },
+                        Literal(".".to_string()),
+                        VersionPart {
+                            update_type: UpdateType::Compatible
+                        }
+                    ],
+                    preset: None,
+                })
+            )
+        }
+
+        #[test]
+        fn parses_build_metadata() {
+            use PatternPart::*;
+            assert_eq!(
+                Pattern::parse("<!>.<>+build<>"),
+                Ok(Pattern {
+                    parts: vec![
+                        VersionPart {
+                            update_type: UpdateType::Breaking
+                        },
                         Literal(".".to_string()),
-                        VersionPart,
+                        VersionPart {
+                            update_type: UpdateType::Compatible
+                        },
+                        Literal("+build".to_string()),
+                        VersionPart {
+                            update_type: UpdateType::Compatible
+                        }
+                    ],
+                    preset: None,
+                })
+            )
+        }
+
+        #[test]
+        fn parses_tilde() {
+            use PatternPart::*;
+            assert_eq!(
+                Pattern::parse("<!>u<>~b<>"),
+                Ok(Pattern {
+                    parts: vec![
+                        VersionPart {
+                            update_type: UpdateType::Breaking
+                        },
+                        Literal("u".to_string()),
+                        VersionPart {
+                            update_type: UpdateType::Compatible
+                        },
+                        Literal("~b".to_string()),
+                        VersionPart {
+                            update_type: UpdateType::Compatible
+                        }
+                    ],
+                    preset: None,
+                })
+            )
+        }
+
+        #[test]
+        fn parses_escaped_tilde() {
+            let pattern = Pattern::parse(r"<>(\~a|~b)").unwrap();
+            assert_eq!(
+                pattern.parts()[1],
+                PatternPart::Alternation {
+                    alternatives: vec!["~a".to_string(), "~b".to_string()],
+                    update_type: UpdateType::Compatible
+                }
+            );
+            assert_eq!(pattern.to_string(), r"<>(\~a|~b)");
+            assert_eq!(
+                Pattern::parse(r"<>(~~a|~b)").unwrap().alternation_types(),
+                vec![UpdateType::Minor]
+            );
+        }
+
+        #[test]
+        fn parses_alternation() {
+            use PatternPart::*;
+            assert_eq!(
+                Pattern::parse("<!>.<>-(slim|slim-bookworm)"),
+                Ok(Pattern {
+                    parts: vec![
+                        VersionPart {
+                            update_type: UpdateType::Breaking
+                        },
                         Literal(".".to_string()),
-                        VersionPart
+                        VersionPart {
+                            update_type: UpdateType::Compatible
+                        },
+                        Literal("-".to_string()),
+                        Alternation {
+                            alternatives: vec!["slim".to_string(), "slim-bookworm".to_string()],
+                            update_type: UpdateType::Compatible
+                        },
                     ],
-                    breaking_degree: 1
+                    preset: None,
                 })
             )
         }
 
+        #[test]
+        fn parses_breaking_alternation() {
+            use PatternPart::*;
+            assert_eq!(
+                Pattern::parse("<!>(!|-alpine)"),
+                Ok(Pattern {
+                    parts: vec![
+                        VersionPart {
+                            update_type: UpdateType::Breaking
+                        },
+                        Alternation {
+                            alternatives: vec!["".to_string(), "-alpine".to_string()],
+                            update_type: UpdateType::Breaking
+                        },
+                    ],
+                    preset: None,
+                })
+            )
+        }
+
+        #[test]
+        fn parses_minor_parts() {
+            assert_eq!(
+                Pattern::parse("<!>.<~>.<>(~|-slim)")
+                    .map(|pattern| (pattern.version_part_types(), pattern.alternation_types())),
+                Ok((
+                    vec![
+                        UpdateType::Breaking,
+                        UpdateType::Minor,
+                        UpdateType::Compatible
+                    ],
+                    vec![UpdateType::Minor]
+                ))
+            );
+        }
+
+        #[test]
+        fn displays_minor_parts() {
+            let raw = "<!>.<~>.<>(~|-slim)";
+            assert_eq!(Pattern::parse(raw).unwrap().to_string(), raw);
+        }
+
+        #[test]
+        fn displays_alternation() {
+            let raw = "<!>.<>(!-alpine|-alpine3.18)";
+            assert_eq!(Pattern::parse(raw).unwrap().to_string(), raw);
+        }
+
+        #[test]
+        fn rejects_single_alternative() {
+            assert!(Pattern::parse("<!>(-alpine)").is_err());
+        }
+
+        #[test]
+        fn parses_preset() {
+            let pattern = Pattern::parse("semver").unwrap();
+            assert_eq!(
+                pattern.parts(),
+                Pattern::parse("<!>.<>.<>").unwrap().parts()
+            );
+            assert_eq!(
+                pattern.version_part_types(),
+                vec![
+                    UpdateType::Breaking,
+                    UpdateType::Compatible,
+                    UpdateType::Compatible
+                ]
+            );
+            assert_eq!(pattern.to_string(), "semver");
+
+            let calver = Pattern::parse("calver").unwrap();
+            assert_eq!(calver.version_part_types(), vec![UpdateType::Compatible; 3]);
+        }
+
+        #[test]
+        fn parses_custom_preset() {
+            let mut presets = Presets::builtin();
+            presets.insert("debian", "<!>.<>-bookworm").unwrap();
+            let pattern = presets.parse("debian").unwrap();
+            assert_eq!(
+                pattern.parts(),
+                Pattern::parse("<!>.<>-bookworm").unwrap().parts()
+            );
+            assert_eq!(pattern.to_string(), "debian");
+        }
+
         #[test]
         fn rejects_invalid_characters() {
             assert_eq!(
@@ -228,8 +608,16 @@ mod parser {
         }
 
         #[test]
-        fn rejects_invalid_break_indicator() {
-            assert_eq!(pattern(r"<>.<!>.<>"), Err(nom::Err::Error(())))
+        fn parses_breaking_part_after_compatible_part() {
+            assert_eq!(
+                Pattern::parse("<>.<>-alpine<!>.<>").map(|pattern| pattern.version_part_types()),
+                Ok(vec![
+                    UpdateType::Compatible,
+                    UpdateType::Compatible,
+                    UpdateType::Breaking,
+                    UpdateType::Compatible
+                ])
+            )
         }
     }
 }
@@ -4,6 +4,7 @@ use crate::Update;
 pub struct Report<NoUpdate, Update, Error> {
     pub no_updates: Vec<NoUpdate>,
     pub compatible_updates: Vec<Update>,
+    pub minor_updates: Vec<Update>,
     pub breaking_updates: Vec<Update>,
     pub failures: Vec<Error>,
 }
@@ -16,6 +17,8 @@ impl<N, U, E> Report<N, U, E> {
             Failure
         } else if !self.breaking_updates.is_empty() {
             BreakingUpdate
+        } else if !self.minor_updates.is_empty() {
+            MinorUpdate
         } else if !self.compatible_updates.is_empty() {
             CompatibleUpdate
         } else {
@@ -27,6 +30,7 @@ impl<N, U, E> Report<N, U, E> {
 pub enum UpdateLevel {
     NoUpdates,
     CompatibleUpdate,
+    MinorUpdate,
     BreakingUpdate,
     Failure,
 }
@@ -38,31 +42,147 @@ pub mod dockerfile {
 
     use itertools::{Either, Itertools};
 
-    use crate::{display_error, image::Image, Tag, Update};
+    use crate::{
+        display_error, dockerfile::ImageReference, image::Image, version::UpdateType, Tag,
+    };
 
     #[derive(Debug)]
     pub struct DockerfileReport<E>
     where
         E: 'static + std::error::Error,
     {
-        pub report: Report<Image, (Image, Tag), (Image, E)>,
+        pub report: Report<(ImageReference, Update), (ImageReference, Update), (ImageReference, E)>,
     }
 
     pub fn format_update(
         current_image: &Image,
-        version_prefix: &'static str,
-        new_tag: &str,
+        update_type: UpdateType,
+        update: &Update,
     ) -> String {
         let image_name = current_image.name.to_string();
+        let version_prefix = version_prefix(update_type);
+        let newer = update.newer.get(update_type);
+        let new_tags = if newer.is_empty() {
+            update.get(update_type).clone().unwrap_or_default()
+        } else {
+            format_tags(newer)
+        };
 
         let prefix_width = std::cmp::max(version_prefix.len(), image_name.len());
-        format!(
-            "{image_name:>width$}:{current_tag}\n{version_prefix:>width$} {new_tag}",
+        let mut output = format!(
+            "{image_name:>width$}:{current_tag}\n{version_prefix:>width$} {new_tags}",
             image_name = image_name,
             current_tag = current_image.tag,
             version_prefix = version_prefix,
-            new_tag = new_tag,
+            new_tags = new_tags,
             width = prefix_width
+        );
+
+        // A ladder with a single step would only repeat the breaking update.
+        if update_type == UpdateType::Breaking && update.breaking_ladder.len() > 1 {
+            output.push_str(&format!(
+                "\n{label:>width$} {ladder}",
+                label = "ladder:",
+                ladder = update.breaking_ladder.join(" -> "),
+                width = prefix_width
+            ));
+        }
+
+        for note in format_notes(update, Some(update_type)) {
+            output.push_str(&format!(
+                "\n{indent:>width$} {note}",
+                indent = "",
+                note = note,
+                width = prefix_width
+            ));
+        }
+
+        output
+    }
+
+    /// Formats the update of an image found in a Dockerfile, noting where its tag is written.
+    pub fn format_reference_update(
+        reference: &ImageReference,
+        update_type: UpdateType,
+        update: &Update,
+    ) -> String {
+        let output = format_update(&reference.image, update_type, update);
+        match output.split_once('\n') {
+            Some((first, rest)) => format!("{} ({})\n{}", first, reference.origin(), rest),
+            None => format!("{} ({})", output, reference.origin()),
+        }
+    }
+
+    /// Formats an image without updates, noting any tags that were skipped.
+    pub fn format_no_update(reference: &ImageReference, update: &Update) -> String {
+        let mut output = reference.to_string();
+        for note in format_notes(update, None) {
+            output.push_str(&format!("\n  {}", note));
+        }
+        output
+    }
+
+    /// Describes the annotation's note and the ignored and pending tags of the `update_type`, or of any type.
+    ///
+    /// Skipped tags of a type without update are described with the most severe update, so that they are listed
+    /// exactly once.
+    pub(super) fn format_notes(update: &Update, update_type: Option<UpdateType>) -> Vec<String> {
+        let is_relevant = |other: UpdateType| {
+            update_type.is_none_or(|update_type| {
+                other == update_type
+                    || update.get(other).is_none() && update.most_severe_type() == Some(update_type)
+            })
+        };
+
+        let note = update.note.iter().map(|note| format!("note: {}", note));
+        let ignored = update
+            .ignored
+            .iter()
+            .filter(|ignored| is_relevant(ignored.update_type))
+            .map(|ignored| match &ignored.reason {
+                Some(reason) => format!("ignored tag {}: {}", ignored.tag, reason),
+                None => format!("ignored tag {}", ignored.tag),
+            });
+        let pending = update
+            .pending
+            .iter()
+            .filter(|pending| is_relevant(pending.update_type))
+            .map(|pending| {
+                format!(
+                    "newer tag {tag} will qualify on {date}",
+                    tag = pending.tag,
+                    date = pending.qualifies_on.format("%Y-%m-%d")
+                )
+            });
+
+        note.chain(ignored).chain(pending).collect()
+    }
+
+    fn version_prefix(update_type: UpdateType) -> &'static str {
+        match update_type {
+            UpdateType::Breaking => "-!>",
+            UpdateType::Minor => "-~>",
+            UpdateType::Compatible => "->",
+        }
+    }
+
+    /// The maximum amount of tags that are listed before the list is collapsed.
+    const COLLAPSE_THRESHOLD: usize = 5;
+    /// The amount of tags kept at each end of a collapsed list.
+    const COLLAPSED_ENDS: usize = 2;
+
+    /// Lists the tags, collapsing the middle of long lists, e.g., `1, 2, ... (3 more), 6, 7`.
+    fn format_tags(tags: &[Tag]) -> String {
+        if tags.len() <= COLLAPSE_THRESHOLD {
+            return tags.join(", ");
+        }
+
+        let hidden = tags.len() - 2 * COLLAPSED_ENDS;
+        format!(
+            "{}, ... ({} more), {}",
+            tags[..COLLAPSED_ENDS].join(", "),
+            hidden,
+            tags[tags.len() - COLLAPSED_ENDS..].join(", ")
         )
     }
 
@@ -70,7 +190,7 @@ pub mod dockerfile {
     where
         E: 'static + std::error::Error,
     {
-        pub fn from(results: impl Iterator<Item = (Image, UpdateResult<E>)>) -> Self {
+        pub fn from(results: impl Iterator<Item = (ImageReference, UpdateResult<E>)>) -> Self {
             let (successes, failures): (Vec<_>, Vec<_>) =
                 results.partition_map(|(image, result)| match result {
                     Ok(info) => Either::Left((image, info)),
@@ -79,33 +199,23 @@ pub mod dockerfile {
 
             let mut no_updates = Vec::new();
             let mut compatible_updates = Vec::new();
+            let mut minor_updates = Vec::new();
             let mut breaking_updates = Vec::new();
 
             for (image, update) in successes {
-                match update {
-                    Update {
-                        breaking: None,
-                        compatible: None,
-                    } => no_updates.push(image),
-                    Update {
-                        breaking: None,
-                        compatible: Some(tag),
-                    } => {
-                        compatible_updates.push((image, tag));
-                    }
-                    Update {
-                        breaking: Some(tag),
-                        compatible: None,
-                    } => {
-                        breaking_updates.push((image, tag));
-                    }
-                    Update {
-                        breaking: Some(breaking),
-                        compatible: Some(compatible),
-                    } => {
-                        compatible_updates.push((image.clone(), compatible));
-                        breaking_updates.push((image, breaking));
-                    }
+                if update.is_empty() {
+                    no_updates.push((image, update));
+                    continue;
+                }
+
+                if update.compatible.is_some() {
+                    compatible_updates.push((image.clone(), update.clone()));
+                }
+                if update.minor.is_some() {
+                    minor_updates.push((image.clone(), update.clone()));
+                }
+                if update.breaking.is_some() {
+                    breaking_updates.push((image, update));
                 }
             }
 
@@ -113,6 +223,7 @@ pub mod dockerfile {
                 report: Report {
                     no_updates,
                     compatible_updates,
+                    minor_updates,
                     breaking_updates,
                     failures,
                 },
@@ -124,19 +235,31 @@ pub mod dockerfile {
                 .report
                 .breaking_updates
                 .iter()
-                .map(|(image, tag)| format_update(image, "-!>", tag))
+                .map(|(reference, update)| {
+                    format_reference_update(reference, UpdateType::Breaking, update)
+                })
+                .collect::<Vec<_>>();
+            let minor_updates = self
+                .report
+                .minor_updates
+                .iter()
+                .map(|(reference, update)| {
+                    format_reference_update(reference, UpdateType::Minor, update)
+                })
                 .collect::<Vec<_>>();
             let compatible_updates = self
                 .report
                 .compatible_updates
                 .iter()
-                .map(|(image, tag)| format_update(image, "->", tag))
+                .map(|(reference, update)| {
+                    format_reference_update(reference, UpdateType::Compatible, update)
+                })
                 .collect::<Vec<_>>();
             let no_updates = self
                 .report
                 .no_updates
                 .iter()
-                .map(|image| image.to_string())
+                .map(|(image, update)| format_no_update(image, update))
                 .collect::<Vec<_>>();
 
             let mut output = Vec::new();
@@ -148,6 +271,13 @@ pub mod dockerfile {
                     breaking_updates.join("\n")
                 ));
             }
+            if !minor_updates.is_empty() {
+                output.push(format!(
+                    "{} minor update(s):\n{}",
+                    minor_updates.len(),
+                    minor_updates.join("\n")
+                ));
+            }
             if !compatible_updates.is_empty() {
                 output.push(format!(
                     "{} compatible update(s):\n{}",
@@ -182,30 +312,38 @@ pub mod dockerfile {
     mod test {
         use super::*;
 
-        use crate::{dockerfile::CheckError, image::ImageName};
+        use std::path::Path;
 
-        type TestDockerfileResults = Vec<(Image, Result<Update, CheckError>)>;
+        use crate::{dockerfile::CheckError, image::ImageName, location::Location, Update};
+
+        /// A reference to the `image` at the start of a Dockerfile.
+        fn reference(image: Image) -> ImageReference {
+            ImageReference::new(image, Location::new(Path::new("Dockerfile"), "", 0..0))
+        }
+
+        type TestDockerfileResults = Vec<(ImageReference, Result<Update, CheckError>)>;
 
         #[test]
         fn generates_dockerfile_report() {
-            let success_image = Image {
+            let success_image = reference(Image {
                 name: ImageName::new(None, "ubuntu".to_string()),
                 tag: "14.04".to_string(),
-            };
-            let success_tag = "14.05".to_string();
+            });
             let success_update = Update {
                 breaking: None,
-                compatible: Some(success_tag.clone()),
+                minor: None,
+                compatible: Some("14.05".to_string()),
+                ..Update::default()
             };
 
-            let fail_image = Image {
+            let fail_image = reference(Image {
                 name: ImageName::new(None, "error".to_string()),
                 tag: "1".to_string(),
-            };
+            });
             let fail_error = CheckError::UnspecifiedPattern;
 
             let input: TestDockerfileResults = vec![
-                (success_image.clone(), Ok(success_update)),
+                (success_image.clone(), Ok(success_update.clone())),
                 (fail_image.clone(), Err(fail_error)),
             ];
 
@@ -216,7 +354,7 @@ pub mod dockerfile {
                     .compatible_updates
                     .into_iter()
                     .collect::<Vec<_>>(),
-                vec![(success_image, success_tag)],
+                vec![(success_image, success_update)],
             );
             assert_eq!(
                 result
@@ -228,6 +366,244 @@ pub mod dockerfile {
                 vec![fail_image]
             );
         }
+
+        #[test]
+        fn reports_each_update_type() {
+            let image = reference(Image {
+                name: ImageName::new(None, "node".to_string()),
+                tag: "14.4.0".to_string(),
+            });
+            let update = Update {
+                compatible: Some("14.4.1".to_string()),
+                minor: Some("14.5.0".to_string()),
+                breaking: Some("15.0.0".to_string()),
+                ..Update::default()
+            };
+
+            let input: TestDockerfileResults = vec![(image.clone(), Ok(update.clone()))];
+
+            let result = DockerfileReport::from(input.into_iter());
+            assert_eq!(
+                result.report.compatible_updates,
+                vec![(image.clone(), update.clone())]
+            );
+            assert_eq!(
+                result.report.minor_updates,
+                vec![(image.clone(), update.clone())]
+            );
+            assert_eq!(result.report.breaking_updates, vec![(image, update)]);
+            assert!(matches!(
+                result.report.update_level(),
+                UpdateLevel::BreakingUpdate
+            ));
+        }
+
+        #[test]
+        fn formats_update() {
+            let image = Image {
+                name: ImageName::new(None, "ubuntu".to_string()),
+                tag: "14.04".to_string(),
+            };
+            let update = Update {
+                compatible: Some("14.07".to_string()),
+                ..Update::default()
+            };
+
+            assert_eq!(
+                format_update(&image, UpdateType::Compatible, &update),
+                "ubuntu:14.04\n    -> 14.07"
+            );
+        }
+
+        #[test]
+        fn formats_breaking_ladder() {
+            let image = Image {
+                name: ImageName::new(None, "node".to_string()),
+                tag: "16.1.0".to_string(),
+            };
+            let update = Update {
+                breaking: Some("20.2.0".to_string()),
+                breaking_ladder: vec!["18.3.1".to_string(), "20.2.0".to_string()],
+                ..Update::default()
+            };
+
+            assert_eq!(
+                format_update(&image, UpdateType::Breaking, &update),
+                "node:16.1.0\n -!> 20.2.0\nladder: 18.3.1 -> 20.2.0"
+            );
+        }
+
+        #[test]
+        fn formats_variable_holding_tag() {
+            use crate::dockerfile::Variable;
+
+            let input = "\nARG NODE_VERSION=18.17.0";
+            let reference = ImageReference {
+                image: Image {
+                    name: ImageName::new(None, "node".to_string()),
+                    tag: "18.17.0-slim".to_string(),
+                },
+                location: Location::new(Path::new("Dockerfile"), input, 18..25),
+                variable: Some(Variable {
+                    keyword: "ARG".to_string(),
+                    name: "NODE_VERSION".to_string(),
+                    prefix: String::new(),
+                    suffix: "-slim".to_string(),
+                }),
+            };
+            let update = Update {
+                compatible: Some("18.17.1-slim".to_string()),
+                ..Update::default()
+            };
+
+            assert_eq!(
+                format_reference_update(&reference, UpdateType::Compatible, &update),
+                "node:18.17.0-slim (ARG NODE_VERSION at Dockerfile:2:18)\n  -> 18.17.1-slim"
+            );
+        }
+
+        #[test]
+        fn formats_skipped_tags() {
+            use chrono::{TimeZone, Utc};
+
+            use crate::{IgnoredTag, PendingTag};
+
+            let image = reference(Image {
+                name: ImageName::new(None, "ubuntu".to_string()),
+                tag: "14.04".to_string(),
+            });
+            let update = Update {
+                pending: vec![PendingTag {
+                    tag: "14.06".to_string(),
+                    update_type: UpdateType::Compatible,
+                    qualifies_on: Utc.with_ymd_and_hms(2022, 6, 15, 12, 0, 0).unwrap(),
+                }],
+                ignored: vec![IgnoredTag {
+                    tag: "14.05".to_string(),
+                    update_type: UpdateType::Compatible,
+                    reason: Some("Yanked upstream".to_string()),
+                }],
+                ..Update::default()
+            };
+
+            assert_eq!(
+                format_no_update(&image, &update),
+                "ubuntu:14.04 (Dockerfile:1:1)\n  ignored tag 14.05: Yanked upstream\n  newer tag 14.06 will qualify on 2022-06-15"
+            );
+        }
+
+        #[test]
+        fn formats_pending_tags_of_types_without_update() {
+            use chrono::{TimeZone, Utc};
+
+            use crate::PendingTag;
+
+            let image = Image {
+                name: ImageName::new(None, "node".to_string()),
+                tag: "18.17.0".to_string(),
+            };
+            let update = Update {
+                compatible: Some("18.17.1".to_string()),
+                minor: Some("18.18.0".to_string()),
+                pending: vec![PendingTag {
+                    tag: "20.0.0".to_string(),
+                    update_type: UpdateType::Breaking,
+                    qualifies_on: Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap(),
+                }],
+                ..Update::default()
+            };
+
+            assert_eq!(
+                format_update(&image, UpdateType::Minor, &update),
+                "node:18.17.0\n -~> 18.18.0\n     newer tag 20.0.0 will qualify on 2023-05-01"
+            );
+            assert_eq!(
+                format_update(&image, UpdateType::Compatible, &update),
+                "node:18.17.0\n  -> 18.17.1"
+            );
+        }
+
+        #[test]
+        fn formats_ignored_tags_of_types_without_update() {
+            use crate::IgnoredTag;
+
+            let image = reference(Image {
+                name: ImageName::new(None, "node".to_string()),
+                tag: "18.17.0".to_string(),
+            });
+            let update = Update {
+                compatible: Some("18.17.1".to_string()),
+                ignored: vec![IgnoredTag {
+                    tag: "20.0.0".to_string(),
+                    update_type: UpdateType::Breaking,
+                    reason: Some("Waiting for the ORM".to_string()),
+                }],
+                ..Update::default()
+            };
+            let input: TestDockerfileResults = vec![(image, Ok(update))];
+
+            assert_eq!(
+                DockerfileReport::from(input.into_iter()).display_successes(),
+                "1 compatible update(s):\nnode:18.17.0 (Dockerfile:1:1)\n  -> 18.17.1\n     ignored tag 20.0.0: Waiting for the ORM"
+            );
+        }
+
+        #[test]
+        fn formats_annotation_note() {
+            let image = Image {
+                name: ImageName::new(None, "ubuntu".to_string()),
+                tag: "14.04".to_string(),
+            };
+            let update = Update {
+                compatible: Some("14.07".to_string()),
+                note: Some("LTS releases only".to_string()),
+                ..Update::default()
+            };
+
+            assert_eq!(
+                format_update(&image, UpdateType::Compatible, &update),
+                "ubuntu:14.04\n    -> 14.07\n       note: LTS releases only"
+            );
+        }
+
+        #[test]
+        fn formats_all_newer_tags() {
+            let image = Image {
+                name: ImageName::new(None, "ubuntu".to_string()),
+                tag: "14.04".to_string(),
+            };
+            let mut update = Update {
+                compatible: Some("14.07".to_string()),
+                ..Update::default()
+            };
+            update.newer.compatible = vec!["14.05", "14.06", "14.07"]
+                .into_iter()
+                .map(String::from)
+                .collect();
+
+            assert_eq!(
+                format_update(&image, UpdateType::Compatible, &update),
+                "ubuntu:14.04\n    -> 14.05, 14.06, 14.07"
+            );
+        }
+
+        #[test]
+        fn collapses_long_lists_of_newer_tags() {
+            let image = Image {
+                name: ImageName::new(None, "ubuntu".to_string()),
+                tag: "14.04".to_string(),
+            };
+            let mut update = Update {
+                compatible: Some("14.11".to_string()),
+                ..Update::default()
+            };
+            update.newer.compatible = (5..=11).map(|minor| format!("14.{:02}", minor)).collect();
+
+            assert_eq!(
+                format_update(&image, UpdateType::Compatible, &update),
+                "ubuntu:14.04\n    -> 14.05, 14.06, ... (3 more), 14.10, 14.11"
+            );
+        }
     }
 }
 
@@ -236,23 +612,32 @@ pub mod docker_compose {
 
     use itertools::Itertools;
 
-    use super::dockerfile::{format_update, DockerfileReport};
+    use super::dockerfile::{
+        format_no_update, format_notes, format_reference_update, format_update, DockerfileReport,
+    };
     use crate::{
         display_error,
         docker_compose::{BuildContext, ServiceName},
+        dockerfile::ImageReference,
         image::Image,
-        Tag,
+        version::UpdateType,
     };
 
     // Trait alias
     pub struct DockerComposeReport<E> {
         #[allow(clippy::type_complexity)]
         pub report: Report<
-            (ServiceName, BuildContext<(), String, Vec<(Image, ())>>),
-            (ServiceName, BuildContext<Tag, String, Vec<(Image, Tag)>>),
             (
                 ServiceName,
-                Result<BuildContext<E, String, Vec<(Image, E)>>, E>,
+                BuildContext<Update, String, Vec<(ImageReference, Update)>>,
+            ),
+            (
+                ServiceName,
+                BuildContext<Update, String, Vec<(ImageReference, Update)>>,
+            ),
+            (
+                ServiceName,
+                Result<BuildContext<E, String, Vec<(ImageReference, E)>>, E>,
             ),
         >,
     }
@@ -265,12 +650,17 @@ pub mod docker_compose {
             results: impl Iterator<
                 Item = (
                     ServiceName,
-                    BuildContext<UpdateResult<E>, String, Result<Vec<(Image, UpdateResult<E>)>, E>>,
+                    BuildContext<
+                        UpdateResult<E>,
+                        String,
+                        Result<Vec<(ImageReference, UpdateResult<E>)>, E>,
+                    >,
                 ),
             >,
         ) -> Self {
             let mut no_updates = Vec::new();
             let mut compatible_updates = Vec::new();
+            let mut minor_updates = Vec::new();
             let mut breaking_updates = Vec::new();
             let mut failures = Vec::new();
 
@@ -280,43 +670,40 @@ pub mod docker_compose {
                         Err(error) => {
                             failures.push((service.clone(), Ok(BuildContext::Image(image, error))))
                         }
-                        Ok(update) => match update {
-                            Update {
-                                compatible: None,
-                                breaking: None,
-                            } => no_updates.push((service, BuildContext::Image(image, ()))),
-                            Update {
-                                compatible,
-                                breaking,
-                            } => {
-                                if let Some(compatible_update) = compatible {
-                                    compatible_updates.push((
-                                        service.clone(),
-                                        BuildContext::Image(image.clone(), compatible_update),
-                                    ));
-                                }
-                                if let Some(breaking_udpate) = breaking {
-                                    breaking_updates.push((
-                                        service.clone(),
-                                        BuildContext::Image(image.clone(), breaking_udpate),
-                                    ));
-                                }
+                        Ok(update) => {
+                            if update.is_empty() {
+                                no_updates.push((service, BuildContext::Image(image, update)));
+                                continue;
+                            }
+
+                            if update.compatible.is_some() {
+                                compatible_updates.push((
+                                    service.clone(),
+                                    BuildContext::Image(image.clone(), update.clone()),
+                                ));
+                            }
+                            if update.minor.is_some() {
+                                minor_updates.push((
+                                    service.clone(),
+                                    BuildContext::Image(image.clone(), update.clone()),
+                                ));
                             }
-                        },
+                            if update.breaking.is_some() {
+                                breaking_updates.push((
+                                    service.clone(),
+                                    BuildContext::Image(image.clone(), update),
+                                ));
+                            }
+                        }
                     },
                     BuildContext::Folder(path, result) => match result {
                         Ok(update_results) => {
                             let report = DockerfileReport::from(update_results.into_iter()).report;
 
                             if !report.no_updates.is_empty() {
-                                let adapted_no_update = report
-                                    .no_updates
-                                    .into_iter()
-                                    .map(|image| (image, ()))
-                                    .collect();
                                 no_updates.push((
                                     service.clone(),
-                                    BuildContext::Folder(path.clone(), adapted_no_update),
+                                    BuildContext::Folder(path.clone(), report.no_updates),
                                 ));
                             }
                             if !report.compatible_updates.is_empty() {
@@ -325,6 +712,12 @@ pub mod docker_compose {
                                     BuildContext::Folder(path.clone(), report.compatible_updates),
                                 ));
                             }
+                            if !report.minor_updates.is_empty() {
+                                minor_updates.push((
+                                    service.clone(),
+                                    BuildContext::Folder(path.clone(), report.minor_updates),
+                                ));
+                            }
                             if !report.breaking_updates.is_empty() {
                                 breaking_updates.push((
                                     service.clone(),
@@ -347,6 +740,7 @@ pub mod docker_compose {
                 report: Report {
                     no_updates,
                     compatible_updates,
+                    minor_updates,
                     breaking_updates,
                     failures,
                 },
@@ -362,12 +756,29 @@ pub mod docker_compose {
                     BuildContext::Image(image, update) => format!(
                         "{service}\n{updates}",
                         service = display_service_image(service, image),
-                        updates = display_update(image, "-!>", update),
+                        updates = display_update(&image.image, UpdateType::Breaking, update),
+                    ),
+                    BuildContext::Folder(service_path, updates) => format!(
+                        "{service}\n{updates}",
+                        service = display_service_folder(service, service_path),
+                        updates = display_updates(UpdateType::Breaking, updates.iter()),
+                    ),
+                })
+                .collect::<Vec<_>>();
+            let minor_updates = self
+                .report
+                .minor_updates
+                .iter()
+                .map(|(service, build_context)| match build_context {
+                    BuildContext::Image(image, update) => format!(
+                        "{service}\n{updates}",
+                        service = display_service_image(service, image),
+                        updates = display_update(&image.image, UpdateType::Minor, update),
                     ),
                     BuildContext::Folder(service_path, updates) => format!(
                         "{service}\n{updates}",
                         service = display_service_folder(service, service_path),
-                        updates = display_updates("-!>", updates.iter()),
+                        updates = display_updates(UpdateType::Minor, updates.iter()),
                     ),
                 })
                 .collect::<Vec<_>>();
@@ -379,12 +790,12 @@ pub mod docker_compose {
                     BuildContext::Image(image, update) => format!(
                         "{service}\n{updates}",
                         service = display_service_image(service, image),
-                        updates = display_update(image, "->", update),
+                        updates = display_update(&image.image, UpdateType::Compatible, update),
                     ),
                     BuildContext::Folder(service_path, updates) => format!(
                         "{service}\n{updates}",
                         service = display_service_folder(service, service_path),
-                        updates = display_updates("->", updates.iter()),
+                        updates = display_updates(UpdateType::Compatible, updates.iter()),
                     ),
                 })
                 .collect::<Vec<_>>();
@@ -393,11 +804,18 @@ pub mod docker_compose {
                 .no_updates
                 .iter()
                 .map(|(service, build_context)| match build_context {
-                    BuildContext::Image(image, ()) => display_service_image(service, image),
+                    BuildContext::Image(image, update) => {
+                        let notes = format_notes(update, None)
+                            .into_iter()
+                            .map(|note| format!("    {}", note));
+                        std::iter::once(display_service_image(service, image))
+                            .chain(notes)
+                            .join("\n")
+                    }
                     BuildContext::Folder(service_path, images) => format!(
                         "{service}\n{images}",
                         service = display_service_folder(service, service_path),
-                        images = display_images(images.iter().map(|(image, ())| image)),
+                        images = display_no_updates(images.iter()),
                     ),
                 })
                 .collect::<Vec<_>>();
@@ -411,6 +829,13 @@ pub mod docker_compose {
                     breaking_updates.join("\n\n")
                 ));
             }
+            if !minor_updates.is_empty() {
+                output.push(format!(
+                    "{} minor update(s):\n{}",
+                    minor_updates.len(),
+                    minor_updates.join("\n\n")
+                ));
+            }
             if !compatible_updates.is_empty() {
                 output.push(format!(
                     "{} compatible update(s):\n{}",
@@ -469,11 +894,12 @@ pub mod docker_compose {
         }
     }
 
-    fn display_service_image(service: &str, image: &Image) -> String {
+    fn display_service_image(service: &str, reference: &ImageReference) -> String {
         format!(
-            "  service `{service}` with image `{image}`:",
+            "  service `{service}` with image `{image}` ({origin}):",
             service = service,
-            image = image
+            image = reference.image,
+            origin = reference.origin()
         )
     }
 
@@ -486,25 +912,36 @@ pub mod docker_compose {
     }
 
     fn display_updates<'a>(
-        version_prefix: &'static str,
-        updates: impl Iterator<Item = &'a (Image, String)>,
+        update_type: UpdateType,
+        updates: impl Iterator<Item = &'a (ImageReference, Update)>,
     ) -> String {
         updates
-            .map(|(image, update)| display_update(image, version_prefix, update))
+            .map(|(reference, update)| {
+                indent_item(&format_reference_update(reference, update_type, update))
+            })
             .join("\n")
     }
 
-    fn display_update(image: &Image, version_prefix: &'static str, update: &str) -> String {
-        let output = format_update(image, version_prefix, update);
-        let indented_output = output.replace('\n', "\n    ");
-        format!("  - {}", indented_output)
+    fn display_update(image: &Image, update_type: UpdateType, update: &Update) -> String {
+        indent_item(&format_update(image, update_type, update))
     }
 
-    fn display_images<'a>(images: impl Iterator<Item = &'a Image>) -> String {
-        images.map(display_image).join("\n")
+    fn indent_item(output: &str) -> String {
+        format!("  - {}", output.replace('\n', "\n    "))
     }
 
-    fn display_image(image: &Image) -> String {
+    fn display_no_updates<'a>(
+        images: impl Iterator<Item = &'a (ImageReference, Update)>,
+    ) -> String {
+        images
+            .map(|(image, update)| {
+                let output = format_no_update(image, update);
+                format!("  - {}", output.replace('\n', "\n    "))
+            })
+            .join("\n")
+    }
+
+    fn display_image(image: &ImageReference) -> String {
         format!("  - {}", image)
     }
 
@@ -512,43 +949,53 @@ pub mod docker_compose {
     mod test {
         use super::*;
 
+        use std::path::Path;
+
         use crate::dockerfile::CheckError;
         use crate::image::ImageName;
+        use crate::location::Location;
         use crate::Update;
 
+        /// A reference to the `image` at the start of a Dockerfile.
+        fn reference(image: Image) -> ImageReference {
+            ImageReference::new(image, Location::new(Path::new("Dockerfile"), "", 0..0))
+        }
+
         #[test]
         fn generates_docker_compose_report() {
             let ubuntu_service = "ubuntu".to_string();
             let ubuntu_path = "/path/to/ubuntu".to_string();
 
-            let compatible_image = Image {
+            let compatible_image = reference(Image {
                 name: ImageName::new(None, "ubuntu".to_string()),
                 tag: "14.04".to_string(),
-            };
-            let compatible_tag = "14.05".to_string();
+            });
             let compatible_update = Update {
                 breaking: None,
-                compatible: Some(compatible_tag.clone()),
+                minor: None,
+                compatible: Some("14.05".to_string()),
+                ..Update::default()
             };
 
-            let fail_image = Image {
+            let fail_image = reference(Image {
                 name: ImageName::new(None, "error".to_string()),
                 tag: "1".to_string(),
-            };
+            });
             let fail_error = CheckError::UnspecifiedPattern;
             let fail_error_copy = CheckError::UnspecifiedPattern;
 
             let alpine_service = "alpine".to_string();
             let alpine_path = "path/to/alpine".to_string();
 
-            let breaking_image = Image {
+            let breaking_image = reference(Image {
                 name: ImageName::new(None, "alpine".to_string()),
                 tag: "3.8.4".to_string(),
-            };
-            let breaking_tag = "4.0.2".to_string();
+            });
             let breaking_update = Update {
                 compatible: None,
-                breaking: Some(breaking_tag.clone()),
+                minor: None,
+                breaking: Some("4.0.2".to_string()),
+                ..Update::default()
             };
 
             let fail_service = "debian".to_string();
@@ -557,21 +1004,22 @@ pub mod docker_compose {
             let fail_service_error_copy = CheckError::UnspecifiedPattern;
 
             let node_service = "node".to_string();
-            let node_image = Image {
+            let node_image = reference(Image {
                 name: ImageName::new(None, "node".to_string()),
                 tag: "14.4.0".to_string(),
-            };
-            let node_compatible_tag = "14.5.0".to_string();
+            });
             let node_compatible_update = Update {
-                compatible: Some(node_compatible_tag.clone()),
+                compatible: Some("14.5.0".to_string()),
+                minor: None,
                 breaking: None,
+                ..Update::default()
             };
 
             let image_fail_service = "python".to_string();
-            let image_fail_image = Image {
+            let image_fail_image = reference(Image {
                 name: ImageName::new(None, "python".to_string()),
                 tag: "3.8.3".to_string(),
-            };
+            });
             let image_fail_error = CheckError::UnspecifiedPattern;
             let image_fail_error_copy = CheckError::UnspecifiedPattern;
 
@@ -581,7 +1029,7 @@ pub mod docker_compose {
                     BuildContext::Folder(
                         ubuntu_path.clone(),
                         Ok(vec![
-                            (compatible_image.clone(), Ok(compatible_update)),
+                            (compatible_image.clone(), Ok(compatible_update.clone())),
                             (fail_image.clone(), Err(fail_error)),
                         ]),
                     ),
@@ -590,7 +1038,7 @@ pub mod docker_compose {
                     alpine_service.clone(),
                     BuildContext::Folder(
                         alpine_path.clone(),
-                        Ok(vec![(breaking_image.clone(), Ok(breaking_update))]),
+                        Ok(vec![(breaking_image.clone(), Ok(breaking_update.clone()))]),
                     ),
                 ),
                 (
@@ -599,7 +1047,7 @@ pub mod docker_compose {
                 ),
                 (
                     node_service.clone(),
-                    BuildContext::Image(node_image.clone(), Ok(node_compatible_update)),
+                    BuildContext::Image(node_image.clone(), Ok(node_compatible_update.clone())),
                 ),
                 (
                     image_fail_service.clone(),
@@ -615,12 +1063,12 @@ pub mod docker_compose {
                         ubuntu_service.clone(),
                         BuildContext::Folder(
                             ubuntu_path.clone(),
-                            vec![(compatible_image, compatible_tag)]
+                            vec![(compatible_image, compatible_update)]
                         )
                     ),
                     (
                         node_service,
-                        BuildContext::Image(node_image, node_compatible_tag)
+                        BuildContext::Image(node_image, node_compatible_update)
                     )
                 ]
             );
@@ -645,7 +1093,7 @@ pub mod docker_compose {
                 result.report.breaking_updates,
                 vec![(
                     alpine_service,
-                    BuildContext::Folder(alpine_path, vec![(breaking_image, breaking_tag)])
+                    BuildContext::Folder(alpine_path, vec![(breaking_image, breaking_update)])
                 )]
             )
         }
@@ -0,0 +1,409 @@
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::annotation::Annotation;
+use crate::dockerfile::ImageReference;
+use crate::location::Location;
+
+type Tag = String;
+
+/// A new tag for an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rewrite {
+    pub reference: ImageReference,
+    pub tag: Tag,
+}
+
+impl Rewrite {
+    /// The text to write in place of the current tag, and where to write it.
+    fn replacement(&self, input: &str) -> Result<(Range<usize>, String), Error> {
+        let reference = &self.reference;
+        let span = reference.location.span.clone();
+        match &reference.variable {
+            Some(variable) => {
+                let value = self
+                    .tag
+                    .strip_prefix(&variable.prefix)
+                    .and_then(|rest| rest.strip_suffix(&variable.suffix))
+                    .ok_or_else(|| Error::TagOutsideVariable {
+                        tag: self.tag.clone(),
+                        variable: variable.to_string(),
+                        prefix: variable.prefix.clone(),
+                        suffix: variable.suffix.clone(),
+                    })?;
+                Ok((span, value.to_string()))
+            }
+            None => {
+                let written = &input[span.clone()];
+                let current = format!(":{}", reference.image.tag);
+                if !written.ends_with(&current) {
+                    return Err(Error::TagNotWrittenOut {
+                        reference: reference.to_string(),
+                    });
+                }
+                let tag_start = span.end - reference.image.tag.len();
+                Ok((tag_start..span.end, self.tag.clone()))
+            }
+        }
+    }
+}
+
+/// Writes the new tags of the `rewrites` into the `input`, keeping everything else as it is.
+///
+/// Several images may share a tag, such as two `FROM`s using the same `ARG`, as long as they agree on the new tag.
+pub fn rewrite(input: &str, rewrites: &[Rewrite]) -> Result<String, Error> {
+    let mut replacements = rewrites
+        .iter()
+        .map(|rewrite| {
+            rewrite
+                .replacement(input)
+                .map(|replacement| (replacement, &rewrite.reference.location))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    replacements.sort_by_key(|((span, _), _)| (span.start, span.end));
+    replacements.dedup_by(|(later, _), (earlier, _)| later == earlier);
+
+    let mut output = String::with_capacity(input.len());
+    let mut end = 0;
+    for ((span, text), location) in replacements {
+        if span.start < end {
+            return Err(Error::ConflictingRewrites {
+                location: location.clone(),
+            });
+        }
+        output.push_str(&input[end..span.start]);
+        output.push_str(&text);
+        end = span.end;
+    }
+    output.push_str(&input[end..]);
+
+    Ok(output)
+}
+
+/// Checks that the images found in the rewritten file carry the new tags of the `rewrites` and still match their patterns.
+///
+/// The `original` and `rewritten` images must be listed in the order they were found in.
+pub fn verify<E>(
+    rewrites: &[Rewrite],
+    original: &[ImageReference],
+    rewritten: &[(ImageReference, Result<Annotation, E>)],
+) -> Result<(), Error> {
+    if original.len() != rewritten.len() {
+        return Err(Error::ChangedImages);
+    }
+
+    for (before, (after, annotation)) in original.iter().zip(rewritten) {
+        let rewrite = match rewrites.iter().find(|rewrite| &rewrite.reference == before) {
+            Some(rewrite) => rewrite,
+            None => continue,
+        };
+        if after.image.name != before.image.name || after.image.tag != rewrite.tag {
+            return Err(Error::UnexpectedTag {
+                expected: rewrite.tag.clone(),
+                reference: after.to_string(),
+            });
+        }
+        let matches = annotation
+            .as_ref()
+            .is_ok_and(|annotation| annotation.extractor.matches(&after.image.tag));
+        if !matches {
+            return Err(Error::PatternMismatch {
+                reference: after.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// The number of unchanged lines shown around each change in a diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// Formats the changes from the `original` to the `rewritten` content of the file at `path` as a unified diff, which `git apply` accepts.
+///
+/// Rewriting only replaces text within lines, so the lines of both contents are compared one by one.
+pub fn unified_diff(path: &str, original: &str, rewritten: &str) -> String {
+    let before = original.split_inclusive('\n').collect::<Vec<_>>();
+    let after = rewritten.split_inclusive('\n').collect::<Vec<_>>();
+    if before == after {
+        return String::new();
+    }
+
+    // Ranges of line indices, which include the changed lines and their context.
+    let mut hunks: Vec<Range<usize>> = Vec::new();
+    if before.len() == after.len() {
+        let changed = (0..before.len()).filter(|&index| before[index] != after[index]);
+        for index in changed {
+            let start = index.saturating_sub(DIFF_CONTEXT);
+            let end = (index + 1 + DIFF_CONTEXT).min(before.len());
+            match hunks.last_mut() {
+                Some(hunk) if hunk.end >= start => hunk.end = end,
+                _ => hunks.push(start..end),
+            }
+        }
+    }
+
+    let mut output = format!("--- a/{path}\n+++ b/{path}\n", path = path);
+    if hunks.is_empty() {
+        // The lines cannot be compared one by one, so the whole content is replaced.
+        output.push_str(&format!("@@ -1,{} +1,{} @@\n", before.len(), after.len()));
+        push_lines(&mut output, '-', &before);
+        push_lines(&mut output, '+', &after);
+        return output;
+    }
+    for hunk in hunks {
+        output.push_str(&format!(
+            "@@ -{start},{count} +{start},{count} @@\n",
+            start = hunk.start + 1,
+            count = hunk.len()
+        ));
+        for index in hunk {
+            if before[index] == after[index] {
+                push_lines(&mut output, ' ', &before[index..=index]);
+            } else {
+                push_lines(&mut output, '-', &before[index..=index]);
+                push_lines(&mut output, '+', &after[index..=index]);
+            }
+        }
+    }
+    output
+}
+
+fn push_lines(output: &mut String, prefix: char, lines: &[&str]) {
+    for line in lines {
+        output.push(prefix);
+        output.push_str(line);
+        if !line.ends_with('\n') {
+            output.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+/// Replaces the content of the file at `path`, such that it is either completely replaced or left untouched.
+///
+/// The content is written to a temporary file next to it, which is then renamed to the `path`.
+pub fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The path is not a file"))?;
+    let mut temporary_name = std::ffi::OsString::from(".");
+    temporary_name.push(file_name);
+    temporary_name.push(".uptag");
+    let temporary_path = path.with_file_name(temporary_name);
+
+    let result = (|| {
+        let mut file = fs::File::create(&temporary_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::set_permissions(&temporary_path, fs::metadata(path)?.permissions())?;
+        fs::rename(&temporary_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary_path);
+    }
+    result
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("The tag of {reference} is not written out, so it cannot be rewritten")]
+    TagNotWrittenOut { reference: String },
+    #[error("The tag `{tag}` does not fit {variable}, which only holds the part between `{prefix}` and `{suffix}`")]
+    TagOutsideVariable {
+        tag: Tag,
+        variable: String,
+        prefix: String,
+        suffix: String,
+    },
+    #[error("Different tags would be written at {location}")]
+    ConflictingRewrites { location: Location },
+    #[error("The rewritten file contains different images than the original")]
+    ChangedImages,
+    #[error("The rewritten image {reference} does not have the expected tag `{expected}`")]
+    UnexpectedTag { expected: Tag, reference: String },
+    #[error("The rewritten image {reference} does not match its pattern anymore")]
+    PatternMismatch { reference: String },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::docker_compose::{self, BuildContext};
+    use crate::dockerfile::{self, CheckError};
+    use crate::pattern::Presets;
+
+    fn parse(input: &str) -> Vec<(ImageReference, Result<Annotation, CheckError>)> {
+        dockerfile::parse(Path::new("Dockerfile"), input, &Presets::builtin()).collect()
+    }
+
+    fn rewrites(input: &str, tags: &[&str]) -> Vec<Rewrite> {
+        parse(input)
+            .into_iter()
+            .zip(tags)
+            .map(|((reference, _), tag)| Rewrite {
+                reference,
+                tag: tag.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rewrites_tags_in_place() {
+        let input = "\
+# uptag --pattern \"<!>.<>.<>\"
+ARG NODE_VERSION=\"18.17.0\"
+
+# A comment that stays.
+# uptag --pattern \"<!>.<>\"
+FROM  ubuntu:18.04 AS base
+FROM node:${NODE_VERSION}-slim
+# uptag --pattern \"<!>.<>\"
+COPY --from=alpine:3.17 /bin /bin
+";
+        let rewrites = rewrites(input, &["20.04", "18.18.2-slim", "3.18"]);
+
+        let output = rewrite(input, &rewrites).unwrap();
+        assert_eq!(
+            output,
+            "\
+# uptag --pattern \"<!>.<>.<>\"
+ARG NODE_VERSION=\"18.18.2\"
+
+# A comment that stays.
+# uptag --pattern \"<!>.<>\"
+FROM  ubuntu:20.04 AS base
+FROM node:${NODE_VERSION}-slim
+# uptag --pattern \"<!>.<>\"
+COPY --from=alpine:3.18 /bin /bin
+"
+        );
+
+        let original = parse(input)
+            .into_iter()
+            .map(|(reference, _)| reference)
+            .collect::<Vec<_>>();
+        assert_eq!(verify(&rewrites, &original, &parse(&output)), Ok(()));
+    }
+
+    #[test]
+    fn rejects_invalid_rewrites() {
+        let input = "\
+# uptag --pattern \"<!>.<>.<>\"
+ARG NODE_VERSION=18.17.0
+FROM node:${NODE_VERSION}-slim
+FROM node:${NODE_VERSION}-alpine
+# uptag --pattern \"<!>.<>\"
+FROM ubuntu:18.04
+";
+        let references = parse(input)
+            .into_iter()
+            .map(|(reference, _)| reference)
+            .collect::<Vec<_>>();
+
+        let outside = rewrites(input, &["18.18.2-alpine"]);
+        assert!(matches!(
+            rewrite(input, &outside),
+            Err(Error::TagOutsideVariable { .. })
+        ));
+
+        let conflicting = rewrites(input, &["18.18.2-slim", "18.18.3-alpine"]);
+        assert!(matches!(
+            rewrite(input, &conflicting),
+            Err(Error::ConflictingRewrites { .. })
+        ));
+
+        let mismatching = vec![Rewrite {
+            reference: references[2].clone(),
+            tag: "focal".to_string(),
+        }];
+        let output = rewrite(input, &mismatching).unwrap();
+        assert!(matches!(
+            verify(&mismatching, &references, &parse(&output)),
+            Err(Error::PatternMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rewrites_compose_files_in_place() {
+        let input = r#"services:
+  ubuntu:
+    # uptag --pattern "<!>.<>"
+    image: "ubuntu:18.04"
+  node:
+    image: node:14.5.0-slim # uptag --pattern "<!>.<>.<>-slim"
+  db:
+    # uptag --pattern "<!>.<>-alpine"
+    image: postgres:${PG_VERSION:-15.3}-alpine
+  alpine:
+    build: ./alpine
+"#;
+        let services =
+            docker_compose::parse(Path::new("docker-compose.yml"), input, &Presets::builtin())
+                .unwrap();
+        let rewrites = services
+            .into_iter()
+            .zip(&["20.04", "14.6.0-slim", "15.4-alpine"])
+            .filter_map(|((_, build_context), tag)| match build_context {
+                BuildContext::Image(reference, _) => Some(Rewrite {
+                    reference,
+                    tag: tag.to_string(),
+                }),
+                BuildContext::Folder(..) => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rewrite(input, &rewrites),
+            Ok(r#"services:
+  ubuntu:
+    # uptag --pattern "<!>.<>"
+    image: "ubuntu:20.04"
+  node:
+    image: node:14.6.0-slim # uptag --pattern "<!>.<>.<>-slim"
+  db:
+    # uptag --pattern "<!>.<>-alpine"
+    image: postgres:${PG_VERSION:-15.4}-alpine
+  alpine:
+    build: ./alpine
+"#
+            .to_string())
+        );
+    }
+
+    #[test]
+    fn formats_unified_diffs() {
+        let original = "# uptag --pattern \"<!>.<>\"\nFROM ubuntu:18.04\nRUN a\nRUN b\nRUN c\nRUN d\nRUN e\nRUN f\nRUN g\nRUN h\nFROM alpine:3.17";
+        let rewritten = original
+            .replace("ubuntu:18.04", "ubuntu:20.04")
+            .replace("alpine:3.17", "alpine:3.18");
+
+        assert_eq!(unified_diff("Dockerfile", original, original), "");
+        assert_eq!(
+            unified_diff("Dockerfile", original, &rewritten),
+            "\
+--- a/Dockerfile
++++ b/Dockerfile
+@@ -1,5 +1,5 @@
+ # uptag --pattern \"<!>.<>\"
+-FROM ubuntu:18.04
++FROM ubuntu:20.04
+ RUN a
+ RUN b
+ RUN c
+@@ -8,4 +8,4 @@
+ RUN f
+ RUN g
+ RUN h
+-FROM alpine:3.17
+\\ No newline at end of file
++FROM alpine:3.18
+\\ No newline at end of file
+"
+        );
+    }
+}
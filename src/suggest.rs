@@ -0,0 +1,208 @@
+use itertools::Itertools;
+
+use crate::pattern::Pattern;
+use crate::version::extractor::VersionExtractor;
+
+type Tag = String;
+
+/// A candidate pattern and how well it fits an image's tags.
+#[derive(Debug, PartialEq)]
+pub struct Suggestion {
+    pub pattern: Pattern,
+    /// The number of tags matching the pattern.
+    pub matches: usize,
+    /// The share of consecutive matching tags whose versions are ordered from newest to oldest.
+    ///
+    /// If less than two tags match, the ordering is considered perfect.
+    pub ordering: f64,
+    /// The first few matching tags.
+    pub samples: Vec<Tag>,
+}
+
+impl Suggestion {
+    /// The number of matches weighted by how well they are ordered.
+    pub fn score(&self) -> f64 {
+        self.matches as f64 * self.ordering
+    }
+}
+
+const SAMPLE_AMOUNT: usize = 5;
+
+/// Ranks the patterns inferred from `current_tag` by how well they fit the `tags`.
+///
+/// The `tags` must be ordered from newest to oldest, like a `TagFetcher` returns them.
+pub fn suggest<S>(current_tag: &str, tags: &[S]) -> Vec<Suggestion>
+where
+    S: AsRef<str>,
+{
+    candidates(current_tag)
+        .into_iter()
+        .map(|pattern| rate(pattern, tags))
+        .sorted_by(|a, b| b.score().total_cmp(&a.score()))
+        .collect()
+}
+
+fn rate<S>(pattern: Pattern, tags: &[S]) -> Suggestion
+where
+    S: AsRef<str>,
+{
+    let extractor = VersionExtractor::new(pattern.clone());
+
+    let matching = tags
+        .iter()
+        .filter(|tag| extractor.matches(tag.as_ref()))
+        .collect::<Vec<_>>();
+    let versions = matching
+        .iter()
+        .filter_map(|tag| extractor.extract_from(tag.as_ref()))
+        .collect::<Vec<_>>();
+
+    let pairs = versions.len().saturating_sub(1);
+    let ordering = if pairs == 0 {
+        1.0
+    } else {
+        let ordered = versions
+            .iter()
+            .tuple_windows()
+            .filter(|(newer, older)| newer >= older)
+            .count();
+        ordered as f64 / pairs as f64
+    };
+
+    Suggestion {
+        matches: matching.len(),
+        ordering,
+        samples: matching
+            .iter()
+            .take(SAMPLE_AMOUNT)
+            .map(|tag| tag.as_ref().to_string())
+            .collect(),
+        pattern,
+    }
+}
+
+/// Infers patterns from the shape of a tag.
+///
+/// Every number becomes a version part, of which the first is breaking.
+/// Additional candidates drop trailing numbers, but keep the tag's suffix,
+/// so that `14.5.0-slim` results in `<!>.<>.<>-slim` and `<!>.<>-slim`.
+pub fn candidates(tag: &str) -> Vec<Pattern> {
+    let (prefix, segments, suffix) = split_numbers(tag);
+    if segments.is_empty() {
+        return Vec::new();
+    }
+
+    let min_parts = std::cmp::min(2, segments.len());
+    (min_parts..=segments.len())
+        .rev()
+        .filter_map(|amount| {
+            let parts = segments
+                .iter()
+                .take(amount)
+                .enumerate()
+                .map(|(index, separator)| {
+                    let version_part = if index == 0 { "<!>" } else { "<>" };
+                    format!("{}{}", separator, version_part)
+                })
+                .join("");
+            Pattern::parse_syntax(&format!("{}{}{}", prefix, parts, suffix)).ok()
+        })
+        .collect()
+}
+
+/// Splits a tag into the text before the first number, the numbers with the text
+/// separating them from the previous number, and the text after the last number.
+fn split_numbers(tag: &str) -> (&str, Vec<&str>, &str) {
+    let mut segments = Vec::new();
+    let mut rest = tag;
+    let mut prefix = None;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        let separator = &rest[..start];
+        match prefix {
+            None => prefix = Some(separator),
+            Some(_) => segments.push(separator),
+        }
+        let after_number = rest[start..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map(|end| start + end)
+            .unwrap_or_else(|| rest.len());
+        rest = &rest[after_number..];
+    }
+
+    match prefix {
+        // The first number has no separator, since it is preceded by the prefix.
+        Some(prefix) => {
+            segments.insert(0, "");
+            (prefix, segments, rest)
+        }
+        None => (tag, segments, ""),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn display(patterns: Vec<Pattern>) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn infers_candidates() {
+        assert_eq!(
+            display(candidates("14.5.0-slim")),
+            vec!["<!>.<>.<>-slim", "<!>.<>-slim"]
+        );
+    }
+
+    #[test]
+    fn infers_candidates_with_prefix() {
+        assert_eq!(display(candidates("v1.2")), vec!["v<!>.<>"]);
+    }
+
+    #[test]
+    fn infers_single_number() {
+        assert_eq!(display(candidates("18")), vec!["<!>"]);
+    }
+
+    #[test]
+    fn infers_nothing_without_numbers() {
+        assert_eq!(candidates("latest"), Vec::new());
+    }
+
+    #[test]
+    fn ranks_suggestions() {
+        let tags = vec![
+            "14.6.0-slim",
+            "14.6-slim",
+            "14.5.1-slim",
+            "14.5.0-slim",
+            "14.5-slim",
+            "14.6.0",
+        ];
+
+        let suggestions = suggest("14.5.0-slim", &tags);
+        assert_eq!(
+            suggestions
+                .iter()
+                .map(|suggestion| (suggestion.pattern.to_string(), suggestion.matches))
+                .collect::<Vec<_>>(),
+            vec![
+                ("<!>.<>.<>-slim".to_string(), 3),
+                ("<!>.<>-slim".to_string(), 2)
+            ]
+        );
+        assert_eq!(
+            suggestions[0].samples,
+            vec!["14.6.0-slim", "14.5.1-slim", "14.5.0-slim"]
+        );
+    }
+
+    #[test]
+    fn rates_ordering() {
+        let tags = vec!["1.2", "1.3", "1.1"];
+
+        let suggestion = rate(Pattern::parse("<!>.<>").unwrap(), &tags);
+        assert_eq!(suggestion.ordering, 0.5);
+    }
+}
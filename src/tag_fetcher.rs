@@ -1,5 +1,6 @@
 use std::collections::VecDeque;
 
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use thiserror::Error;
 
@@ -7,7 +8,7 @@ use crate::image::ImageName;
 
 /// Enables fetching of tags belonging to an image.
 pub trait TagFetcher {
-    type TagIter: IntoIterator<Item = Result<Tag, Self::FetchError>>;
+    type TagIter: IntoIterator<Item = Result<TagInfo, Self::FetchError>>;
     type FetchError: std::error::Error;
 
     /// Constructs a fallible iterator over the `image`'s tags ordered
@@ -38,9 +39,22 @@ struct Response {
     next: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-struct TagInfo {
-    name: String,
+/// A tag together with the metadata uptag uses to judge it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TagInfo {
+    pub name: Tag,
+    /// When the tag was last pushed, if known.
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+impl TagInfo {
+    /// A tag without any known metadata.
+    pub fn new(name: Tag) -> Self {
+        TagInfo {
+            name,
+            last_updated: None,
+        }
+    }
 }
 
 type Tag = String;
@@ -69,7 +83,7 @@ const FETCH_AMOUNT: usize = 25;
 pub struct DockerHubTagIterator {
     image_name: ImageName,
     /// The tags of the current page.
-    fetched: VecDeque<Tag>,
+    fetched: VecDeque<TagInfo>,
     current_page: CurrentPage,
 }
 
@@ -117,7 +131,7 @@ impl DockerHubTagIterator {
 type DockerHubTagIteratorError = reqwest::Error;
 
 impl Iterator for DockerHubTagIterator {
-    type Item = Result<Tag, DockerHubTagFetcherError>;
+    type Item = Result<TagInfo, DockerHubTagFetcherError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if !self.fetched.is_empty() {
@@ -141,11 +155,7 @@ impl Iterator for DockerHubTagIterator {
                 .and_then(|response| {
                     log::info!("Fetch was successful.");
 
-                    let mut tags = response
-                        .results
-                        .into_iter()
-                        .map(|info| info.name)
-                        .collect::<VecDeque<_>>();
+                    let mut tags = response.results.into_iter().collect::<VecDeque<_>>();
 
                     // If the image name is invalid, we will get a 200 OK, but
                     // with an empty tag list. For details, see https://github.com/Y0hy0h/uptag/issues/37
@@ -196,7 +206,7 @@ pub mod test {
 
     #[derive(Debug, PartialEq, Default)]
     pub struct ArrayFetcher {
-        content: HashMap<ImageName, Vec<Tag>>,
+        content: HashMap<ImageName, Vec<TagInfo>>,
     }
 
     impl ArrayFetcher {
@@ -207,6 +217,10 @@ pub mod test {
         }
 
         pub fn with(image_name: ImageName, tags: Vec<Tag>) -> ArrayFetcher {
+            Self::with_infos(image_name, tags.into_iter().map(TagInfo::new).collect())
+        }
+
+        pub fn with_infos(image_name: ImageName, tags: Vec<TagInfo>) -> ArrayFetcher {
             let mut content = HashMap::new();
             content.insert(image_name, tags);
             ArrayFetcher { content }
@@ -214,7 +228,7 @@ pub mod test {
     }
 
     impl TagFetcher for ArrayFetcher {
-        type TagIter = Vec<Result<Tag, Self::FetchError>>;
+        type TagIter = Vec<Result<TagInfo, Self::FetchError>>;
         type FetchError = FetchError;
 
         fn fetch(&self, image: &ImageName) -> Self::TagIter {
@@ -1,60 +1,145 @@
 use itertools::Itertools;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 use crate::pattern;
 use crate::pattern::Pattern;
+use extractor::VersionExtractor;
 
+/// Versions are ordered by their parts first and then by their variants.
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Version {
     parts: Vec<VersionPart>,
+    /// The index of the chosen alternative for each alternation.
+    variants: Vec<usize>,
 }
 
 type VersionPart = usize;
 
 impl Version {
     pub fn new(parts: Vec<VersionPart>) -> Option<Version> {
+        Self::with_variants(parts, Vec::new())
+    }
+
+    pub fn with_variants(parts: Vec<VersionPart>, variants: Vec<usize>) -> Option<Version> {
         if parts.is_empty() {
             None
         } else {
-            Some(Version { parts })
+            Some(Version { parts, variants })
         }
     }
 
-    pub fn update_type(&self, other: &Self, breaking_degree: usize) -> UpdateType {
-        if self.sameness_degree_with(other) >= breaking_degree {
-            UpdateType::Compatible
-        } else {
-            UpdateType::Breaking
+    /// The numbers of the version, in the order they appear in the tag.
+    pub fn parts(&self) -> &[VersionPart] {
+        &self.parts
+    }
+
+    /// Determines the type of update by the most severe of the changed segments, as given by the `extractor`.
+    pub fn update_type(&self, other: &Self, extractor: &VersionExtractor) -> UpdateType {
+        fn changed_types<'a, T: PartialEq>(
+            left: &'a [T],
+            right: &'a [T],
+            types: Vec<UpdateType>,
+        ) -> impl Iterator<Item = UpdateType> + 'a {
+            left.iter()
+                .zip(right.iter())
+                .zip(types)
+                .filter(|((l, r), _)| l != r)
+                .map(|(_, update_type)| update_type)
         }
+
+        changed_types(&self.parts, &other.parts, extractor.version_part_types())
+            .chain(changed_types(
+                &self.variants,
+                &other.variants,
+                extractor.alternation_types(),
+            ))
+            .max()
+            .unwrap_or(UpdateType::Compatible)
     }
 
-    fn sameness_degree_with(&self, other: &Self) -> usize {
-        self.parts
-            .iter()
-            .zip(other.parts.iter())
-            .take_while(|(l, r)| l == r)
-            .count()
+    /// The values of the breaking parts and alternations, which identify the release line of this version.
+    ///
+    /// Versions differing only in non-breaking parts belong to the same line, e.g., `18.1.0` and `18.2.3` for `<!>.<>.<>`.
+    pub fn breaking_line(&self, extractor: &VersionExtractor) -> Vec<usize> {
+        fn breaking_values<'a>(
+            values: &'a [usize],
+            types: Vec<UpdateType>,
+        ) -> impl Iterator<Item = usize> + 'a {
+            values
+                .iter()
+                .zip(types)
+                .filter(|(_, update_type)| *update_type == UpdateType::Breaking)
+                .map(|(value, _)| *value)
+        }
+
+        breaking_values(&self.parts, extractor.version_part_types())
+            .chain(breaking_values(
+                &self.variants,
+                extractor.alternation_types(),
+            ))
+            .collect()
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// The types of updates, ordered by severity.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
 pub enum UpdateType {
     Compatible,
+    Minor,
     Breaking,
 }
 
+impl std::str::FromStr for UpdateType {
+    type Err = InvalidUpdateType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compatible" => Ok(UpdateType::Compatible),
+            "minor" => Ok(UpdateType::Minor),
+            "breaking" => Ok(UpdateType::Breaking),
+            _ => Err(InvalidUpdateType(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("The update type `{0}` is invalid (expected `compatible`, `minor` or `breaking`)")]
+pub struct InvalidUpdateType(String);
+
 pub mod extractor {
     use super::*;
 
+    use thiserror::Error;
+
     #[derive(Debug, Clone)]
     pub struct VersionExtractor {
-        pattern: Pattern,
+        source: Source,
         regex: Regex,
+        /// What each capture group of the `regex` extracts, in order.
+        groups: Vec<Group>,
+    }
+
+    /// What a `VersionExtractor` was created from.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Source {
+        Pattern(Pattern),
+        /// The regular expression as given by the user.
+        Regex(String),
+    }
+
+    #[derive(Debug, Clone)]
+    enum Group {
+        VersionPart(UpdateType),
+        Alternation {
+            alternatives: Vec<String>,
+            update_type: UpdateType,
+        },
     }
 
     impl PartialEq for VersionExtractor {
         fn eq(&self, other: &Self) -> bool {
-            self.pattern == other.pattern
+            self.source == other.source
         }
     }
 
@@ -67,6 +152,15 @@ pub mod extractor {
         }
     }
 
+    impl std::fmt::Display for VersionExtractor {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match &self.source {
+                Source::Pattern(pattern) => write!(f, "{}", pattern),
+                Source::Regex(raw_regex) => write!(f, "{}", raw_regex),
+            }
+        }
+    }
+
     pub trait Tagged {
         fn tag(&self) -> &str;
     }
@@ -82,8 +176,77 @@ pub mod extractor {
 
     impl VersionExtractor {
         pub fn new(pattern: Pattern) -> VersionExtractor {
+            use pattern::PatternPart;
+
             let regex = Self::regex_for_pattern(&pattern);
-            VersionExtractor { pattern, regex }
+            let groups = pattern
+                .parts()
+                .iter()
+                .filter_map(|part| match part {
+                    PatternPart::VersionPart { update_type } => {
+                        Some(Group::VersionPart(*update_type))
+                    }
+                    PatternPart::Alternation {
+                        alternatives,
+                        update_type,
+                    } => Some(Group::Alternation {
+                        alternatives: alternatives.clone(),
+                        update_type: *update_type,
+                    }),
+                    PatternPart::Literal(_) => None,
+                })
+                .collect();
+            VersionExtractor {
+                source: Source::Pattern(pattern),
+                regex,
+                groups,
+            }
+        }
+
+        /// Creates an extractor from a regular expression that has to match the entire tag.
+        ///
+        /// Each capture group has to match a number. Its name determines the type of update
+        /// that changing the number causes: Names starting with `breaking` or `minor` mark
+        /// breaking or minor updates, while names starting with `compatible` and unnamed
+        /// groups mark compatible updates.
+        pub fn from_regex(raw_regex: &str) -> Result<VersionExtractor, RegexError> {
+            let regex = Regex::new(&format!("^(?:{})$", raw_regex))?;
+            let groups = regex
+                .capture_names()
+                .skip(1) // The first group is always the entire match.
+                .map(|name| {
+                    let update_type = match name {
+                        None => UpdateType::Compatible,
+                        Some(name) if name.starts_with("breaking") => UpdateType::Breaking,
+                        Some(name) if name.starts_with("minor") => UpdateType::Minor,
+                        Some(name) if name.starts_with("compatible") => UpdateType::Compatible,
+                        Some(name) => return Err(RegexError::UnknownGroup(name.to_string())),
+                    };
+                    Ok(Group::VersionPart(update_type))
+                })
+                .collect::<Result<_, _>>()?;
+
+            Ok(VersionExtractor {
+                source: Source::Regex(raw_regex.to_string()),
+                regex,
+                groups,
+            })
+        }
+
+        /// Requires the version to be surrounded by the literal `prefix` and `suffix`.
+        pub fn surrounded_by(&self, prefix: &str, suffix: &str) -> VersionExtractor {
+            match &self.source {
+                Source::Pattern(pattern) => {
+                    VersionExtractor::new(pattern.surrounded_by(prefix, suffix))
+                }
+                Source::Regex(raw_regex) => VersionExtractor::from_regex(&format!(
+                    "{}(?:{}){}",
+                    regex::escape(prefix),
+                    raw_regex,
+                    regex::escape(suffix)
+                ))
+                .unwrap(), // Escaped literals add no groups, so the regex stays valid.
+            }
         }
 
         pub fn regex_for_pattern(pattern: &Pattern) -> Regex {
@@ -93,7 +256,14 @@ pub mod extractor {
                 .iter()
                 .map(|part| match part {
                     Literal(literal) => Self::escape_literal(literal),
-                    VersionPart => r"(\d+)".to_string(),
+                    VersionPart { .. } => r"(\d+)".to_string(),
+                    Alternation { alternatives, .. } => format!(
+                        "({})",
+                        alternatives
+                            .iter()
+                            .map(|alternative| Self::escape_literal(alternative))
+                            .join("|")
+                    ),
                 })
                 .join("");
             let raw_regex = format!("^{}$", inner_regex);
@@ -102,7 +272,7 @@ pub mod extractor {
         }
 
         fn escape_literal(literal: &str) -> String {
-            literal.replace('.', r"\.")
+            regex::escape(literal)
         }
 
         pub fn parse<'a, S>(pattern: S) -> Result<VersionExtractor, pattern::Error>
@@ -113,15 +283,49 @@ pub mod extractor {
             Ok(extractor)
         }
 
-        pub fn pattern(&self) -> &Pattern {
-            &self.pattern
+        pub fn source(&self) -> &Source {
+            &self.source
+        }
+
+        /// The pattern this extractor was created from, unless it was created from a regular expression.
+        pub fn pattern(&self) -> Option<&Pattern> {
+            match &self.source {
+                Source::Pattern(pattern) => Some(pattern),
+                Source::Regex(_) => None,
+            }
+        }
+
+        /// The type of update that changing the number causes, for each version part in order.
+        pub fn version_part_types(&self) -> Vec<UpdateType> {
+            self.groups
+                .iter()
+                .filter_map(|group| match group {
+                    Group::VersionPart(update_type) => Some(*update_type),
+                    Group::Alternation { .. } => None,
+                })
+                .collect()
+        }
+
+        /// The type of update that changing between alternatives causes, for each alternation in order.
+        pub fn alternation_types(&self) -> Vec<UpdateType> {
+            self.groups
+                .iter()
+                .filter_map(|group| match group {
+                    Group::VersionPart(_) => None,
+                    Group::Alternation { update_type, .. } => Some(*update_type),
+                })
+                .collect()
         }
 
         pub fn matches<T>(&self, candidate: T) -> bool
         where
             T: Tagged,
         {
-            self.regex.is_match(candidate.tag().as_ref())
+            match self.source {
+                Source::Pattern(_) => self.regex.is_match(candidate.tag()),
+                // The capture groups of a user-supplied regex might match something other than numbers.
+                Source::Regex(_) => self.extract_from(candidate).is_some(),
+            }
         }
 
         pub fn extract_from<T>(&self, candidate: T) -> Option<Version>
@@ -129,25 +333,38 @@ pub mod extractor {
             T: Tagged,
         {
             let tag = candidate.tag();
-            let parts = self
-                .regex
-                .captures(tag) // Only look at the first match.
-                .into_iter()
-                .flat_map(|captures| {
-                    captures
-                        .iter()
-                        .skip(1) // We are only interested in the capture groups, so we skip the first submatch, since that contains the entire match.
-                        .filter_map(|maybe_submatch| {
-                            maybe_submatch
-                                .map(|submatch| submatch.as_str().parse::<VersionPart>().unwrap())
-                        })
-                        .collect::<Vec<_>>()
-                })
-                .collect();
-            Version::new(parts)
+            let captures = self.regex.captures(tag)?; // Only look at the first match.
+
+            let mut parts = Vec::new();
+            let mut variants = Vec::new();
+            // We are only interested in the capture groups, so we skip the first submatch, since that contains the entire match.
+            let submatches = captures.iter().skip(1);
+            for (group, maybe_submatch) in self.groups.iter().zip(submatches) {
+                let submatch = maybe_submatch?.as_str();
+                match group {
+                    Group::VersionPart(_) => parts.push(submatch.parse::<VersionPart>().ok()?),
+                    Group::Alternation { alternatives, .. } => variants.push(
+                        alternatives
+                            .iter()
+                            .position(|alternative| alternative == submatch)?,
+                    ),
+                }
+            }
+
+            Version::with_variants(parts, variants)
         }
     }
 
+    #[derive(Debug, Error, PartialEq)]
+    pub enum RegexError {
+        #[error(transparent)]
+        Invalid(#[from] regex::Error),
+        #[error(
+            "The capture group name `{0}` does not start with `breaking`, `minor` or `compatible`"
+        )]
+        UnknownGroup(String),
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
@@ -175,6 +392,7 @@ pub mod extractor {
                 let other = other.borrow();
                 Version {
                     parts: vec![other.0, other.1, other.2],
+                    variants: Vec::new(),
                 }
             }
         }
@@ -237,6 +455,176 @@ pub mod extractor {
             }
         }
 
+        #[test]
+        fn extracts_with_build_metadata() {
+            let extractor = VersionExtractor::parse("<!>.<>.<>+build<>").unwrap();
+            assert_eq!(
+                extractor.extract_from("1.2.3+build5"),
+                Version::new(vec![1, 2, 3, 5])
+            );
+            assert!(!extractor.matches("1.2.3build5"));
+            assert!(!extractor.matches("1.2.3++build5"));
+        }
+
+        #[test]
+        fn extracts_with_tilde() {
+            let extractor = VersionExtractor::parse("<!>u<>~b<>").unwrap();
+            assert_eq!(
+                extractor.extract_from("8u292~b10"),
+                Version::new(vec![8, 292, 10])
+            );
+        }
+
+        #[test]
+        fn extracts_with_leading_literal() {
+            let extractor = VersionExtractor::parse("v<!>.<>").unwrap();
+            assert_eq!(extractor.extract_from("v1.2"), Version::new(vec![1, 2]));
+            assert!(!extractor.matches("1.2"));
+        }
+
+        #[test]
+        fn escapes_literal_periods() {
+            let extractor = VersionExtractor::parse("<!>.<>").unwrap();
+            assert!(!extractor.matches("1a2"));
+        }
+
+        #[test]
+        fn extracts_alternation() {
+            let extractor = VersionExtractor::parse("<!>.<>-(slim|slim-bookworm)").unwrap();
+            assert_eq!(
+                extractor.extract_from("18.2-slim"),
+                Version::with_variants(vec![18, 2], vec![0])
+            );
+            assert_eq!(
+                extractor.extract_from("18.2-slim-bookworm"),
+                Version::with_variants(vec![18, 2], vec![1])
+            );
+            assert!(!extractor.matches("18.2-bookworm"));
+        }
+
+        #[test]
+        fn detects_alternation_update_type() {
+            let compatible = VersionExtractor::parse("<!>.<>(|-bookworm)").unwrap();
+            let current = compatible.extract_from("18.2").unwrap();
+            let candidate = compatible.extract_from("18.2-bookworm").unwrap();
+            assert_eq!(
+                candidate.update_type(&current, &compatible),
+                UpdateType::Compatible
+            );
+
+            let breaking = VersionExtractor::parse("<!>.<>(!|-bookworm)").unwrap();
+            assert_eq!(
+                candidate.update_type(&current, &breaking),
+                UpdateType::Breaking
+            );
+        }
+
+        #[test]
+        fn detects_minor_update() {
+            let extractor = VersionExtractor::parse("<!>.<~>.<>").unwrap();
+            let current = extractor.extract_from("1.2.3").unwrap();
+
+            let cases = vec![
+                ("1.2.4", UpdateType::Compatible),
+                ("1.3.0", UpdateType::Minor),
+                ("1.3.4", UpdateType::Minor),
+                ("2.3.4", UpdateType::Breaking),
+            ];
+            for (candidate, expected) in cases {
+                let candidate_version = extractor.extract_from(candidate).unwrap();
+                assert_eq!(
+                    candidate_version.update_type(&current, &extractor),
+                    expected,
+                    "{}",
+                    candidate
+                );
+            }
+        }
+
+        #[test]
+        fn detects_non_prefix_breaking_update() {
+            let extractor = VersionExtractor::parse("<>.<>-alpine<!>.<>").unwrap();
+            let current = extractor.extract_from("1.2-alpine3.18").unwrap();
+
+            let compatible = extractor.extract_from("2.0-alpine3.19").unwrap();
+            assert_eq!(
+                compatible.update_type(&current, &extractor),
+                UpdateType::Compatible
+            );
+
+            let breaking = extractor.extract_from("1.2-alpine4.0").unwrap();
+            assert_eq!(
+                breaking.update_type(&current, &extractor),
+                UpdateType::Breaking
+            );
+        }
+
+        #[test]
+        fn determines_breaking_line() {
+            let extractor = VersionExtractor::parse("<>.<!>-(!slim|bookworm).<>").unwrap();
+            let version = extractor.extract_from("1.18-bookworm.3").unwrap();
+            assert_eq!(version.breaking_line(&extractor), vec![18, 1]);
+        }
+
+        #[test]
+        fn surrounds_with_literals() {
+            let extractor = VersionExtractor::parse("semver").unwrap();
+            let surrounded = extractor.surrounded_by("v", "-slim");
+            assert_eq!(surrounded.to_string(), "v<!>.<>.<>-slim");
+            assert!(surrounded.matches("v18.17.0-slim"));
+
+            let extractor = VersionExtractor::from_regex(r"(?P<breaking>\d+)u(\d+)").unwrap();
+            let surrounded = extractor.surrounded_by("", "-jre");
+            assert!(surrounded.matches("8u292-jre"));
+            assert!(!surrounded.matches("8u292"));
+        }
+
+        #[test]
+        fn extracts_with_regex() {
+            let extractor =
+                VersionExtractor::from_regex(r"(?P<breaking>\d+)\.(\d+)-r(?P<minor>\d+)").unwrap();
+            let current = extractor.extract_from("1.2-r3").unwrap();
+            assert_eq!(Some(&current), Version::new(vec![1, 2, 3]).as_ref());
+            assert!(!extractor.matches("1.2-r3-extra"));
+
+            let cases = vec![
+                ("1.3-r3", UpdateType::Compatible),
+                ("1.2-r4", UpdateType::Minor),
+                ("2.2-r3", UpdateType::Breaking),
+            ];
+            for (candidate, expected) in cases {
+                let candidate_version = extractor.extract_from(candidate).unwrap();
+                assert_eq!(
+                    candidate_version.update_type(&current, &extractor),
+                    expected,
+                    "{}",
+                    candidate
+                );
+            }
+        }
+
+        #[test]
+        fn rejects_overflowing_version_parts() {
+            let overflowing = "0.18450000000000000000.0";
+            assert_eq!(STRICT_SEMVER.extract_from(overflowing), None);
+            let extractor = VersionExtractor::from_regex(r"(\d+)\.(\d+)\.(\d+)").unwrap();
+            assert!(!extractor.matches(overflowing));
+        }
+
+        #[test]
+        fn rejects_regex_capturing_non_numbers() {
+            let extractor = VersionExtractor::from_regex(r"(\d+)-(\w+)").unwrap();
+            assert!(!extractor.matches("1-alpine"));
+        }
+
+        #[test]
+        fn rejects_unknown_regex_group() {
+            assert!(matches!(
+                VersionExtractor::from_regex(r"(?P<major>\d+)"),
+                Err(RegexError::UnknownGroup(name)) if name == "major"
+            ));
+        }
+
         // Comparison
 
         prop_compose! {
@@ -281,6 +669,11 @@ pub mod extractor {
             }
         }
 
+        lazy_static! {
+            static ref FIVE_PARTS: VersionExtractor =
+                VersionExtractor::parse("<!>.<!>.<>.<>.<>").unwrap();
+        }
+
         proptest! {
             #[test]
             fn detects_greater_version(
@@ -291,12 +684,12 @@ pub mod extractor {
 
             #[test]
             fn detects_comptaible_update((smaller, greater) in version_seq_no_break(5, 2)) {
-                prop_assert_eq!(smaller.update_type(&greater, 2), UpdateType::Compatible);
+                prop_assert_eq!(smaller.update_type(&greater, &FIVE_PARTS), UpdateType::Compatible);
             }
 
             #[test]
             fn detects_breaking_update((smaller, greater) in version_seq_with_break(5, 2)) {
-                prop_assert_eq!(smaller.update_type(&greater, 2), UpdateType::Breaking);
+                prop_assert_eq!(smaller.update_type(&greater, &FIVE_PARTS), UpdateType::Breaking);
             }
         }
     }